@@ -0,0 +1,103 @@
+use std::io::{self, Write};
+
+use visioncortex::Color;
+
+use crate::segments::path_data;
+use crate::svg::SvgFile;
+
+impl SvgFile {
+    /// Write an Android VectorDrawable, one dp per pixel. Paths have their offset baked into
+    /// `android:pathData`, as VectorDrawable paths cannot be transformed.
+    pub fn write_vector_drawable<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        writeln!(writer, r#"<?xml version="1.0" encoding="utf-8"?>"#)?;
+        writeln!(
+            writer,
+            r#"<!-- Generator: visioncortex VTracer {} -->"#,
+            env!("CARGO_PKG_VERSION")
+        )?;
+        writeln!(
+            writer,
+            r#"<vector xmlns:android="http://schemas.android.com/apk/res/android""#
+        )?;
+        writeln!(writer, r#"    android:width="{}dp""#, self.width)?;
+        writeln!(writer, r#"    android:height="{}dp""#, self.height)?;
+        writeln!(writer, r#"    android:viewportWidth="{}""#, self.width)?;
+        writeln!(writer, r#"    android:viewportHeight="{}">"#, self.height)?;
+        for path in &self.paths {
+            if path.path.paths.is_empty() {
+                continue;
+            }
+            writeln!(writer, "    <path")?;
+            writeln!(
+                writer,
+                r#"        android:fillColor="{}""#,
+                argb_hex_string(&path.color)
+            )?;
+            writeln!(
+                writer,
+                r#"        android:pathData="{}"/>"#,
+                path_data(&path.path, self.path_precision)
+            )?;
+        }
+        writeln!(writer, "</vector>")
+    }
+
+    /// Write a Kotlin file declaring a Jetpack Compose `ImageVector` named `name`, one dp per pixel
+    pub fn write_image_vector<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        name: &str,
+    ) -> io::Result<()> {
+        writeln!(
+            writer,
+            "// Generator: visioncortex VTracer {}",
+            env!("CARGO_PKG_VERSION")
+        )?;
+        writeln!(writer)?;
+        writeln!(writer, "import androidx.compose.ui.graphics.Color")?;
+        writeln!(writer, "import androidx.compose.ui.graphics.SolidColor")?;
+        writeln!(
+            writer,
+            "import androidx.compose.ui.graphics.vector.ImageVector"
+        )?;
+        writeln!(
+            writer,
+            "import androidx.compose.ui.graphics.vector.addPathNodes"
+        )?;
+        writeln!(writer, "import androidx.compose.ui.unit.dp")?;
+        writeln!(writer)?;
+        writeln!(writer, "val {}: ImageVector = ImageVector.Builder(", name)?;
+        writeln!(writer, "    name = \"{}\",", name)?;
+        writeln!(writer, "    defaultWidth = {}.dp,", self.width)?;
+        writeln!(writer, "    defaultHeight = {}.dp,", self.height)?;
+        writeln!(writer, "    viewportWidth = {}f,", self.width)?;
+        writeln!(writer, "    viewportHeight = {}f,", self.height)?;
+        writeln!(writer, ").apply {{")?;
+        for path in &self.paths {
+            if path.path.paths.is_empty() {
+                continue;
+            }
+            writeln!(writer, "    addPath(")?;
+            writeln!(
+                writer,
+                "        pathData = addPathNodes(\"{}\"),",
+                path_data(&path.path, self.path_precision)
+            )?;
+            writeln!(
+                writer,
+                "        fill = SolidColor(Color(0x{})),",
+                &argb_hex_string(&path.color)[1..]
+            )?;
+            writeln!(writer, "    )")?;
+        }
+        writeln!(writer, "}}.build()")
+    }
+}
+
+/// `#AARRGGBB`, the color notation of Android resources
+fn argb_hex_string(color: &Color) -> String {
+    format!(
+        "#{:02X}{:02X}{:02X}{:02X}",
+        color.a, color.r, color.g, color.b
+    )
+}
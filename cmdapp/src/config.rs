@@ -1,33 +1,56 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
 use std::str::FromStr;
 use visioncortex::PathSimplifyMode;
 
-#[derive(Debug, Clone)]
+use crate::error::{InvalidField, VtracerError};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Preset {
     Bw,
     Poster,
     Photo,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ColorMode {
     Color,
+    #[serde(alias = "bw")]
     Binary,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Hierarchical {
     Stacked,
     Cutout,
 }
 
+/// Mirror of `PathSimplifyMode` for serde, using the same names as the cmd app
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "PathSimplifyMode", rename_all = "lowercase")]
+enum PathSimplifyModeDef {
+    #[serde(alias = "pixel")]
+    None,
+    Polygon,
+    Spline,
+}
+
 /// Converter config
-#[derive(Debug, Clone)]
+///
+/// Fields missing from a serialized config take their values from `Config::default()`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
     pub color_mode: ColorMode,
     pub hierarchical: Hierarchical,
     pub filter_speckle: usize,
     pub color_precision: i32,
     pub layer_difference: i32,
+    #[serde(with = "PathSimplifyModeDef")]
     pub mode: PathSimplifyMode,
     pub corner_threshold: i32,
     pub length_threshold: f64,
@@ -36,8 +59,9 @@ pub struct Config {
     pub path_precision: Option<u32>,
 }
 
+/// `Config` with every value converted into the unit used by clustering and path fitting
 #[derive(Debug, Clone)]
-pub(crate) struct ConverterConfig {
+pub struct ConverterConfig {
     pub color_mode: ColorMode,
     pub hierarchical: Hierarchical,
     pub filter_speckle_area: usize,
@@ -70,42 +94,65 @@ impl Default for Config {
 }
 
 impl FromStr for ColorMode {
-    type Err = String;
+    type Err = InvalidField;
 
+    /// Case insensitive, as `BW` has always been accepted
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
+        match s.to_ascii_lowercase().as_str() {
             "color" => Ok(Self::Color),
-            "binary" => Ok(Self::Binary),
-            _ => Err(format!("unknown ColorMode {}", s)),
+            "binary" | "bw" => Ok(Self::Binary),
+            _ => Err(InvalidField::new(
+                "color_mode",
+                s,
+                "`color`, `binary` or `bw`",
+            )),
         }
     }
 }
 
 impl FromStr for Hierarchical {
-    type Err = String;
+    type Err = InvalidField;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "stacked" => Ok(Self::Stacked),
             "cutout" => Ok(Self::Cutout),
-            _ => Err(format!("unknown Hierarchical {}", s)),
+            _ => Err(InvalidField::new(
+                "hierarchical",
+                s,
+                "`stacked` or `cutout`",
+            )),
         }
     }
 }
 
 impl FromStr for Preset {
-    type Err = String;
+    type Err = InvalidField;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "bw" => Ok(Self::Bw),
             "poster" => Ok(Self::Poster),
             "photo" => Ok(Self::Photo),
-            _ => Err(format!("unknown Preset {}", s)),
+            _ => Err(InvalidField::new("preset", s, "`bw`, `poster` or `photo`")),
         }
     }
 }
 
+/// Parse a curve fitting mode. `pixel` is accepted as another name for `none`.
+pub fn path_simplify_mode_from_str(s: &str) -> Result<PathSimplifyMode, InvalidField> {
+    match s {
+        "none" | "pixel" => Ok(PathSimplifyMode::None),
+        "polygon" => Ok(PathSimplifyMode::Polygon),
+        "spline" => Ok(PathSimplifyMode::Spline),
+        _ => Err(InvalidField::new(
+            "mode",
+            s,
+            "`pixel`, `none`, `polygon` or `spline`",
+        )),
+    }
+}
+
 impl Config {
     pub fn from_preset(preset: Preset) -> Self {
         match preset {
@@ -151,7 +198,89 @@ impl Config {
         }
     }
 
-    pub(crate) fn into_converter_config(self) -> ConverterConfig {
+    /// Read a config file, parsed as JSON if it has a `.json` extension and as TOML otherwise.
+    /// Keys that are not fields of `Config` are rejected, as they are most likely typos.
+    pub fn from_file(path: &Path) -> Result<Self, VtracerError> {
+        let string = fs::read_to_string(path)?;
+        let is_json = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+        let (config, keys): (Self, serde_json::Value) = if is_json {
+            (
+                serde_json::from_str(&string)
+                    .map_err(|err| VtracerError::ConfigParse(Box::new(err)))?,
+                serde_json::from_str(&string)
+                    .map_err(|err| VtracerError::ConfigParse(Box::new(err)))?,
+            )
+        } else {
+            (
+                toml::from_str(&string).map_err(|err| VtracerError::ConfigParse(Box::new(err)))?,
+                toml::from_str(&string).map_err(|err| VtracerError::ConfigParse(Box::new(err)))?,
+            )
+        };
+        check_keys(&keys)?;
+        Ok(config)
+    }
+
+    /// Check every field against its accepted range, reporting all offending fields at once
+    pub fn validate(&self) -> Result<(), VtracerError> {
+        let invalid = self.invalid_fields();
+        if invalid.is_empty() {
+            Ok(())
+        } else {
+            Err(VtracerError::InvalidConfig(invalid))
+        }
+    }
+
+    pub(crate) fn invalid_fields(&self) -> Vec<InvalidField> {
+        let mut invalid = vec![];
+        if self.filter_speckle > 16 {
+            invalid.push(InvalidField::new(
+                "filter_speckle",
+                self.filter_speckle,
+                "within [0,16]",
+            ));
+        }
+        if !(1..=8).contains(&self.color_precision) {
+            invalid.push(InvalidField::new(
+                "color_precision",
+                self.color_precision,
+                "within [1,8]",
+            ));
+        }
+        if !(0..=255).contains(&self.layer_difference) {
+            invalid.push(InvalidField::new(
+                "layer_difference",
+                self.layer_difference,
+                "within [0,255]",
+            ));
+        }
+        if !(0..=180).contains(&self.corner_threshold) {
+            invalid.push(InvalidField::new(
+                "corner_threshold",
+                self.corner_threshold,
+                "within [0,180]",
+            ));
+        }
+        // Also rejects NaN
+        if !(3.5..=10.0).contains(&self.length_threshold) {
+            invalid.push(InvalidField::new(
+                "length_threshold",
+                self.length_threshold,
+                "within [3.5,10]",
+            ));
+        }
+        if !(0..=180).contains(&self.splice_threshold) {
+            invalid.push(InvalidField::new(
+                "splice_threshold",
+                self.splice_threshold,
+                "within [0,180]",
+            ));
+        }
+        invalid
+    }
+
+    pub fn into_converter_config(self) -> ConverterConfig {
         ConverterConfig {
             color_mode: self.color_mode,
             hierarchical: self.hierarchical,
@@ -168,6 +297,108 @@ impl Config {
     }
 }
 
+/// Reject the keys of a config file that are not fields of `Config`. `Config` cannot deny them
+/// itself, as the webapp params flatten it together with their own fields.
+fn check_keys(keys: &serde_json::Value) -> Result<(), VtracerError> {
+    let fields = serde_json::to_value(Config::default()).expect("Config serializes to a map");
+    let (keys, fields) = match (keys.as_object(), fields.as_object()) {
+        (Some(keys), Some(fields)) => (keys, fields),
+        _ => return Ok(()),
+    };
+    let unknown: Vec<String> = keys
+        .keys()
+        .filter(|key| !fields.contains_key(*key))
+        .map(|key| format!("`{}`", key))
+        .collect();
+    if unknown.is_empty() {
+        return Ok(());
+    }
+    let expected: Vec<String> = fields.keys().map(|field| format!("`{}`", field)).collect();
+    Err(VtracerError::ConfigParse(
+        format!(
+            "unknown field {}, expected one of {}",
+            unknown.join(", "),
+            expected.join(", ")
+        )
+        .into(),
+    ))
+}
+
 fn deg2rad(deg: i32) -> f64 {
     deg as f64 / 180.0 * std::f64::consts::PI
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_lists_every_invalid_field() {
+        assert!(Config::default().validate().is_ok());
+        let config = Config {
+            filter_speckle: 17,
+            color_precision: 0,
+            layer_difference: 256,
+            corner_threshold: -1,
+            length_threshold: f64::NAN,
+            splice_threshold: 181,
+            ..Config::default()
+        };
+        let fields = match config.validate() {
+            Err(VtracerError::InvalidConfig(fields)) => fields,
+            other => panic!("expected an invalid config, got {:?}", other),
+        };
+        let names: Vec<_> = fields.iter().map(|field| field.field).collect();
+        assert_eq!(
+            names,
+            [
+                "filter_speckle",
+                "color_precision",
+                "layer_difference",
+                "corner_threshold",
+                "length_threshold",
+                "splice_threshold"
+            ]
+        );
+    }
+
+    fn from_file(name: &str, contents: &str) -> Result<Config, VtracerError> {
+        let path = std::env::temp_dir().join(format!("vtracer-{}-{}", std::process::id(), name));
+        fs::write(&path, contents).unwrap();
+        let config = Config::from_file(&path);
+        fs::remove_file(&path).unwrap();
+        config
+    }
+
+    #[test]
+    fn config_files_reject_unknown_keys() {
+        let config = from_file("config.toml", "filter_speckle = 10\nmode = \"pixel\"\n").unwrap();
+        assert_eq!(config.filter_speckle, 10);
+        assert!(matches!(config.mode, PathSimplifyMode::None));
+        let config = from_file("config.json", r#"{"color_precision": 8}"#).unwrap();
+        assert_eq!(config.color_precision, 8);
+
+        for (name, contents) in [
+            ("typo.toml", "filter_speckel = 10\n"),
+            ("typo.json", r#"{"filter_speckel": 10}"#),
+        ] {
+            match from_file(name, contents) {
+                Err(VtracerError::ConfigParse(err)) => {
+                    assert!(err
+                        .to_string()
+                        .starts_with("unknown field `filter_speckel`"))
+                }
+                other => panic!("expected a parse error, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn color_mode_ignores_case() {
+        for s in ["bw", "BW", "Binary"] {
+            assert!(matches!(s.parse(), Ok(ColorMode::Binary)));
+        }
+        assert!(matches!("COLOR".parse(), Ok(ColorMode::Color)));
+        assert!("grey".parse::<ColorMode>().is_err());
+    }
+}
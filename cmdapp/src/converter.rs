@@ -1,69 +1,170 @@
+use std::io::Cursor;
+use std::ops::ControlFlow;
 use std::path::Path;
-use std::{fs::File, io::Write};
 
 use super::config::{ColorMode, Config, ConverterConfig, Hierarchical};
-use super::svg::SvgFile;
-use fastrand::Rng;
-use visioncortex::color_clusters::{KeyingAction, Runner, RunnerConfig, HIERARCHICAL_MAX};
-use visioncortex::{Color, ColorImage, ColorName};
+use super::error::VtracerError;
+use super::output::{write_output_file, OutputFormat};
+use super::svg::{PathMetadata, SvgFile, SvgOptions, SvgPath};
+use image::{io::Reader, DynamicImage, ImageFormat, RgbaImage};
+use visioncortex::clusters::Clusters as BinaryClusters;
+use visioncortex::color_clusters::{
+    ClusterIndex, Clusters, ClustersView, IncrementalBuilder, KeyingAction, Runner, RunnerConfig,
+    HIERARCHICAL_MAX,
+};
+use visioncortex::{BinaryImage, Color, ColorImage, ColorName};
 
-const NUM_UNUSED_COLOR_ITERATIONS: usize = 6;
 /// The fraction of pixels in the top/bottom rows of the image that need to be transparent before
 /// the entire image will be keyed.
 const KEYING_THRESHOLD: f32 = 0.2;
 
 /// Convert an in-memory image into an in-memory SVG
-pub fn convert(img: ColorImage, config: Config) -> Result<SvgFile, String> {
-    let config = config.into_converter_config();
-    match config.color_mode {
-        ColorMode::Color => color_image_to_svg(img, config),
-        ColorMode::Binary => binary_image_to_svg(img, config),
+pub fn convert(img: ColorImage, config: Config) -> Result<SvgFile, VtracerError> {
+    Ok(Converter::new(img, config)?.run())
+}
+
+/// Convert an in-memory image into an in-memory SVG, calling `on_progress` before the first step
+/// and after every step. The conversion stops with `VtracerError::Cancelled` as soon as
+/// `on_progress` breaks.
+pub fn convert_with_progress<F>(
+    img: ColorImage,
+    config: Config,
+    mut on_progress: F,
+) -> Result<SvgFile, VtracerError>
+where
+    F: FnMut(Progress) -> ControlFlow<()>,
+{
+    let mut converter = Converter::new(img, config)?;
+    let mut done = false;
+    loop {
+        if on_progress(converter.progress()).is_break() {
+            return Err(VtracerError::Cancelled);
+        }
+        if done {
+            return Ok(converter.run());
+        }
+        done = converter.tick();
     }
 }
 
-/// Convert an image file into svg file
+/// Decode an encoded image (png, jpg, ...) and convert it into an in-memory SVG.
+/// The format is guessed from the contents when `format` is `None`.
+pub fn convert_bytes(
+    bytes: &[u8],
+    format: Option<ImageFormat>,
+    config: Config,
+) -> Result<SvgFile, VtracerError> {
+    let img = decode_image(bytes, format)?;
+    convert(img, config)
+}
+
+/// Convert a decoded image of any pixel format into an in-memory SVG
+pub fn convert_dynamic_image(img: DynamicImage, config: Config) -> Result<SvgFile, VtracerError> {
+    convert_rgba_image(img.into_rgba8(), config)
+}
+
+/// Convert an RGBA image into an in-memory SVG
+pub fn convert_rgba_image(img: RgbaImage, config: Config) -> Result<SvgFile, VtracerError> {
+    convert(rgba_to_color_image(img), config)
+}
+
+/// Convert an image file into svg file, gzip compressed if `output_path` ends in `.svgz`
 pub fn convert_image_to_svg(
     input_path: &Path,
     output_path: &Path,
     config: Config,
-) -> Result<(), String> {
+) -> Result<(), VtracerError> {
     let img = read_image(input_path)?;
     let svg = convert(img, config)?;
-    write_svg(svg, output_path)
+    let format = match OutputFormat::from_path(output_path) {
+        Some(format @ OutputFormat::Svgz(_)) => format,
+        _ => OutputFormat::Svg(SvgOptions::default()),
+    };
+    write_output_file(&svg, output_path, &format)
 }
 
-fn color_exists_in_image(img: &ColorImage, color: Color) -> bool {
-    for y in 0..img.height {
-        for x in 0..img.width {
-            let pixel_color = img.get_pixel(x, y);
-            if pixel_color.r == color.r && pixel_color.g == color.g && pixel_color.b == color.b {
-                return true;
-            }
+/// Presence of every 24-bit RGB color in an image, one bit per color
+struct ColorSet {
+    bits: Vec<u64>,
+}
+
+impl ColorSet {
+    fn from_image(img: &ColorImage) -> Self {
+        let mut set = Self {
+            bits: vec![0; (1 << 24) / 64],
+        };
+        for pixel in img.pixels.chunks_exact(4) {
+            set.insert(Color::new(pixel[0], pixel[1], pixel[2]));
         }
+        set
+    }
+
+    fn index(color: Color) -> usize {
+        (color.r as usize) << 16 | (color.g as usize) << 8 | color.b as usize
+    }
+
+    fn insert(&mut self, color: Color) {
+        let i = Self::index(color);
+        self.bits[i / 64] |= 1 << (i % 64);
+    }
+
+    fn contains(&self, color: Color) -> bool {
+        let i = Self::index(color);
+        self.bits[i / 64] & (1 << (i % 64)) != 0
+    }
+
+    /// The unused color with the lowest RGB value
+    fn first_unused(&self) -> Option<Color> {
+        let (word, bits) = self
+            .bits
+            .iter()
+            .enumerate()
+            .find(|(_, &bits)| bits != u64::MAX)?;
+        let i = word * 64 + bits.trailing_ones() as usize;
+        Some(Color::new((i >> 16) as u8, (i >> 8) as u8, i as u8))
     }
-    false
 }
 
-fn find_unused_color_in_image(img: &ColorImage) -> Result<Color, String> {
-    let special_colors = IntoIterator::into_iter([
+/// Pick a key color absent from the image. The choice depends only on the colors present,
+/// so the same image is always keyed the same way.
+fn find_unused_color_in_image(img: &ColorImage) -> Result<Color, VtracerError> {
+    let special_colors = [
         Color::new(255, 0, 0),
         Color::new(0, 255, 0),
         Color::new(0, 0, 255),
         Color::new(255, 255, 0),
         Color::new(0, 255, 255),
         Color::new(255, 0, 255),
-    ]);
-    let rng = Rng::new();
-    let random_colors =
-        (0..NUM_UNUSED_COLOR_ITERATIONS).map(|_| Color::new(rng.u8(..), rng.u8(..), rng.u8(..)));
-    for color in special_colors.chain(random_colors) {
-        if !color_exists_in_image(img, color) {
-            return Ok(color);
+    ];
+    let mut used = ColorSet::from_image(img);
+    if let Some(color) = special_colors
+        .into_iter()
+        .find(|&color| !used.contains(color))
+    {
+        return Ok(color);
+    }
+    // Black would be mistaken for `Color::default()`, which means no keying
+    used.insert(Color::new(0, 0, 0));
+    used.first_unused().ok_or(VtracerError::Keying)
+}
+
+/// Replace the transparent pixels with a color the image does not otherwise use, if enough of
+/// the image is transparent. Returns the key color, or `Color::default()` if the image is not keyed.
+pub fn key_image(img: &mut ColorImage) -> Result<Color, VtracerError> {
+    if !should_key_image(img) {
+        // The default color is all zeroes, which is treated by visioncortex as a special value meaning no keying will be applied.
+        return Ok(Color::default());
+    }
+
+    let key_color = find_unused_color_in_image(img)?;
+    for y in 0..img.height {
+        for x in 0..img.width {
+            if img.get_pixel(x, y).a == 0 {
+                img.set_pixel(x, y, &key_color);
+            }
         }
     }
-    Err(String::from(
-        "unable to find unused color in image to use as key",
-    ))
+    Ok(key_color)
 }
 
 fn should_key_image(img: &ColorImage) -> bool {
@@ -95,143 +196,500 @@ fn should_key_image(img: &ColorImage) -> bool {
     false
 }
 
-fn color_image_to_svg(mut img: ColorImage, config: ConverterConfig) -> Result<SvgFile, String> {
-    let width = img.width;
-    let height = img.height;
+/// Stage of a conversion, in the order they are run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Clustering,
+    /// Only run in `Hierarchical::Cutout` mode
+    Reclustering,
+    Vectorizing,
+    Done,
+}
+
+/// Progress of a conversion, `percent` is the overall progress in `[0,100]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    pub stage: Stage,
+    pub percent: u32,
+}
 
-    let key_color = if should_key_image(&img) {
-        let key_color = find_unused_color_in_image(&img)?;
-        for y in 0..height {
-            for x in 0..width {
-                if img.get_pixel(x, y).a == 0 {
-                    img.set_pixel(x, y, &key_color);
-                }
-            }
-        }
-        key_color
-    } else {
-        // The default color is all zeroes, which is treated by visioncortex as a special value meaning no keying will be applied.
-        Color::default()
-    };
+/// A conversion that runs a step at a time, so that the caller can report progress
+/// and abandon it midway by simply dropping it
+pub struct Converter {
+    config: ConverterConfig,
+    state: State,
+    key_color: Color,
+    counter: usize,
+    svg: SvgFile,
+    /// The highest progress so far, as clustering does not progress steadily
+    progress: Progress,
+}
 
-    let runner = Runner::new(
-        RunnerConfig {
-            diagonal: config.layer_difference == 0,
-            hierarchical: HIERARCHICAL_MAX,
-            batch_size: 25600,
-            good_min_area: config.filter_speckle_area,
-            good_max_area: (width * height),
-            is_same_color_a: config.color_precision_loss,
-            is_same_color_b: 1,
-            deepen_diff: config.layer_difference,
-            hollow_neighbours: 1,
+enum State {
+    Clustering(IncrementalBuilder),
+    ClusteringBinary(BinaryImage),
+    Reclustering(IncrementalBuilder),
+    Vectorizing(Clusters),
+    VectorizingBinary(BinaryClusters),
+    Done,
+}
+
+impl Converter {
+    /// Fails with `VtracerError::InvalidConfig` if `config` does not pass `Config::validate`
+    pub fn new(img: ColorImage, config: Config) -> Result<Self, VtracerError> {
+        config.validate()?;
+        let config = config.into_converter_config();
+        let svg = SvgFile::new(img.width, img.height, config.path_precision);
+        let (state, key_color) = match config.color_mode {
+            ColorMode::Color => Self::start_clustering(img, &config)?,
+            ColorMode::Binary => (
+                State::ClusteringBinary(img.to_binary_image(|x| x.r < 128)),
+                Color::default(),
+            ),
+        };
+        Ok(Self {
+            config,
+            state,
             key_color,
-            keying_action: if matches!(config.hierarchical, Hierarchical::Cutout) {
-                KeyingAction::Keep
-            } else {
-                KeyingAction::Discard
+            counter: 0,
+            svg,
+            progress: Progress {
+                stage: Stage::Clustering,
+                percent: 0,
             },
-        },
-        img,
-    );
+        })
+    }
+
+    fn start_clustering(
+        mut img: ColorImage,
+        config: &ConverterConfig,
+    ) -> Result<(State, Color), VtracerError> {
+        // Nothing would be left to cluster, and the builder's progress divides by what is left
+        if img.pixels.chunks_exact(4).all(|pixel| pixel[3] == 0) {
+            return Ok((State::Done, Color::default()));
+        }
+
+        let width = img.width;
+        let height = img.height;
+        let key_color = key_image(&mut img)?;
 
-    let mut clusters = runner.run();
-
-    match config.hierarchical {
-        Hierarchical::Stacked => {}
-        Hierarchical::Cutout => {
-            let view = clusters.view();
-            let image = view.to_color_image();
-            let runner = Runner::new(
-                RunnerConfig {
-                    diagonal: false,
-                    hierarchical: 64,
-                    batch_size: 25600,
-                    good_min_area: 0,
-                    good_max_area: (image.width * image.height) as usize,
-                    is_same_color_a: 0,
-                    is_same_color_b: 1,
-                    deepen_diff: 0,
-                    hollow_neighbours: 0,
-                    key_color,
-                    keying_action: KeyingAction::Discard,
+        let runner = Runner::new(
+            RunnerConfig {
+                diagonal: config.layer_difference == 0,
+                hierarchical: HIERARCHICAL_MAX,
+                batch_size: 25600,
+                good_min_area: config.filter_speckle_area,
+                good_max_area: (width * height),
+                is_same_color_a: config.color_precision_loss,
+                is_same_color_b: 1,
+                deepen_diff: config.layer_difference,
+                hollow_neighbours: 1,
+                key_color,
+                keying_action: if matches!(config.hierarchical, Hierarchical::Cutout) {
+                    KeyingAction::Keep
+                } else {
+                    KeyingAction::Discard
                 },
-                image,
-            );
-            clusters = runner.run();
+            },
+            img,
+        );
+
+        Ok((State::Clustering(runner.start()), key_color))
+    }
+
+    /// Run one step of the conversion. Returns true once the conversion is done.
+    pub fn tick(&mut self) -> bool {
+        let done = self.step();
+        let Progress { stage, percent } = self.current_progress();
+        self.progress = Progress {
+            stage,
+            percent: percent.max(self.progress.percent),
+        };
+        done
+    }
+
+    fn step(&mut self) -> bool {
+        match &mut self.state {
+            State::Clustering(builder) => {
+                if builder.tick() {
+                    let clusters = builder.result();
+                    self.state = match self.config.hierarchical {
+                        Hierarchical::Stacked => State::Vectorizing(clusters),
+                        Hierarchical::Cutout => {
+                            let view = clusters.view();
+                            let image = view.to_color_image();
+                            let runner = Runner::new(
+                                RunnerConfig {
+                                    diagonal: false,
+                                    hierarchical: 64,
+                                    batch_size: 25600,
+                                    good_min_area: 0,
+                                    good_max_area: (image.width * image.height),
+                                    is_same_color_a: 0,
+                                    is_same_color_b: 1,
+                                    deepen_diff: 0,
+                                    hollow_neighbours: 0,
+                                    key_color: self.key_color,
+                                    keying_action: KeyingAction::Discard,
+                                },
+                                image,
+                            );
+                            State::Reclustering(runner.start())
+                        }
+                    };
+                }
+                false
+            }
+            State::Reclustering(builder) => {
+                if builder.tick() {
+                    self.state = State::Vectorizing(builder.result());
+                }
+                false
+            }
+            State::ClusteringBinary(img) => {
+                self.state = State::VectorizingBinary(img.to_clusters(false));
+                false
+            }
+            State::Vectorizing(clusters) => {
+                let view = clusters.view();
+                let len = view.clusters_output.len();
+                if self.counter < len {
+                    let end = (self.counter + vectorize_batch_size()).min(len);
+                    // Paths are stacked from the last output cluster to the first
+                    let batch = &view.clusters_output[len - end..len - self.counter];
+                    self.svg
+                        .paths
+                        .extend(fit_clusters(&view, batch, &self.config));
+                    self.counter = end;
+                    false
+                } else {
+                    self.state = State::Done;
+                    true
+                }
+            }
+            State::VectorizingBinary(clusters) => {
+                if self.counter < clusters.len() {
+                    let cluster = clusters.get_cluster(self.counter);
+                    if cluster.size() >= self.config.filter_speckle_area {
+                        let paths = cluster.to_compound_path(
+                            self.config.mode,
+                            self.config.corner_threshold,
+                            self.config.length_threshold,
+                            self.config.max_iterations,
+                            self.config.splice_threshold,
+                        );
+                        let metadata = PathMetadata {
+                            area: cluster.size(),
+                            rect: cluster.rect,
+                            cluster_index: self.counter as u32,
+                            depth: 0,
+                        };
+                        self.svg.add_path_with_metadata(
+                            paths,
+                            Color::color(&ColorName::Black),
+                            metadata,
+                        );
+                    }
+                    self.counter += 1;
+                    false
+                } else {
+                    self.state = State::Done;
+                    true
+                }
+            }
+            State::Done => true,
         }
     }
 
-    let view = clusters.view();
+    /// Progress of the steps run so far, which never goes backwards
+    pub fn progress(&self) -> Progress {
+        self.progress
+    }
 
-    let mut svg = SvgFile::new(width, height, config.path_precision);
-    for &cluster_index in view.clusters_output.iter().rev() {
-        let cluster = view.get_cluster(cluster_index);
-        let paths = cluster.to_compound_path(
-            &view,
-            false,
-            config.mode,
-            config.corner_threshold,
-            config.length_threshold,
-            config.max_iterations,
-            config.splice_threshold,
-        );
-        svg.add_path(paths, cluster.residue_color());
+    fn current_progress(&self) -> Progress {
+        // Clustering takes up the first half, split with reclustering in cutout mode
+        let clustering_share = match self.config.hierarchical {
+            Hierarchical::Stacked => 50,
+            Hierarchical::Cutout => 25,
+        };
+        let (stage, percent) = match &self.state {
+            State::Clustering(builder) => (
+                Stage::Clustering,
+                builder.progress() * clustering_share / 100,
+            ),
+            State::Reclustering(builder) => (Stage::Reclustering, 25 + builder.progress() / 4),
+            State::Vectorizing(clusters) => {
+                let len = clusters.view().clusters_output.len();
+                (Stage::Vectorizing, 50 + percent_of(self.counter, len) / 2)
+            }
+            // Binary clustering is a single step
+            State::ClusteringBinary(_) => (Stage::Clustering, 0),
+            State::VectorizingBinary(clusters) => (
+                Stage::Vectorizing,
+                50 + percent_of(self.counter, clusters.len()) / 2,
+            ),
+            State::Done => (Stage::Done, 100),
+        };
+        Progress { stage, percent }
     }
 
-    Ok(svg)
+    /// Run the remaining steps and return the svg
+    pub fn run(mut self) -> SvgFile {
+        while !self.tick() {}
+        self.svg
+    }
 }
 
-fn binary_image_to_svg(img: ColorImage, config: ConverterConfig) -> Result<SvgFile, String> {
-    let img = img.to_binary_image(|x| x.r < 128);
-    let width = img.width;
-    let height = img.height;
+/// Number of clusters fitted in one step of the vectorizing stage
+#[cfg(not(feature = "parallel"))]
+fn vectorize_batch_size() -> usize {
+    1
+}
 
-    let clusters = img.to_clusters(false);
+/// Number of clusters fitted in one step of the vectorizing stage,
+/// large enough to keep every thread busy
+#[cfg(feature = "parallel")]
+fn vectorize_batch_size() -> usize {
+    16 * rayon::current_num_threads()
+}
 
-    let mut svg = SvgFile::new(width, height, config.path_precision);
-    for i in 0..clusters.len() {
-        let cluster = clusters.get_cluster(i);
-        if cluster.size() >= config.filter_speckle_area {
-            let paths = cluster.to_compound_path(
-                config.mode,
-                config.corner_threshold,
-                config.length_threshold,
-                config.max_iterations,
-                config.splice_threshold,
-            );
-            svg.add_path(paths, Color::color(&ColorName::Black));
-        }
+/// Fit `batch` in reverse order, returning the paths in that same order
+#[cfg(not(feature = "parallel"))]
+fn fit_clusters(
+    view: &ClustersView,
+    batch: &[ClusterIndex],
+    config: &ConverterConfig,
+) -> Vec<SvgPath> {
+    batch
+        .iter()
+        .rev()
+        .map(|&index| fit_cluster(view, index, config))
+        .collect()
+}
+
+/// Fit `batch` in reverse order across threads, returning the paths in that same order
+#[cfg(feature = "parallel")]
+fn fit_clusters(
+    view: &ClustersView,
+    batch: &[ClusterIndex],
+    config: &ConverterConfig,
+) -> Vec<SvgPath> {
+    use rayon::prelude::*;
+
+    batch
+        .par_iter()
+        .rev()
+        .map(|&index| fit_cluster(view, index, config))
+        .collect()
+}
+
+fn fit_cluster(view: &ClustersView, index: ClusterIndex, config: &ConverterConfig) -> SvgPath {
+    let cluster = view.get_cluster(index);
+    let paths = cluster.to_compound_path(
+        view,
+        false,
+        config.mode,
+        config.corner_threshold,
+        config.length_threshold,
+        config.max_iterations,
+        config.splice_threshold,
+    );
+    SvgPath {
+        path: paths,
+        color: cluster.residue_color(),
+        metadata: PathMetadata {
+            area: cluster.area(),
+            rect: cluster.rect,
+            cluster_index: index.0,
+            depth: cluster.depth,
+        },
     }
+}
+
+fn percent_of(count: usize, total: usize) -> u32 {
+    (100 * count)
+        .checked_div(total)
+        .map_or(100, |percent| percent as u32)
+}
 
-    Ok(svg)
+/// Read an image file into memory
+pub fn read_image(input_path: &Path) -> Result<ColorImage, VtracerError> {
+    let img = image::open(input_path)?.into_rgba8();
+    Ok(rgba_to_color_image(img))
 }
 
-fn read_image(input_path: &Path) -> Result<ColorImage, String> {
-    let img = image::open(input_path);
-    let img = match img {
-        Ok(file) => file.to_rgba8(),
-        Err(_) => return Err(String::from("No image file found at specified input path")),
+fn decode_image(bytes: &[u8], format: Option<ImageFormat>) -> Result<ColorImage, VtracerError> {
+    let mut reader = Reader::new(Cursor::new(bytes));
+    let img = match format {
+        Some(format) => {
+            reader.set_format(format);
+            reader.decode()?
+        }
+        None => reader.with_guessed_format()?.decode()?,
     };
+    Ok(rgba_to_color_image(img.into_rgba8()))
+}
 
+fn rgba_to_color_image(img: RgbaImage) -> ColorImage {
     let (width, height) = (img.width() as usize, img.height() as usize);
-    let img = ColorImage {
-        pixels: img.as_raw().to_vec(),
+    ColorImage {
+        pixels: img.into_raw(),
         width,
         height,
-    };
-
-    Ok(img)
+    }
 }
 
-fn write_svg(svg: SvgFile, output_path: &Path) -> Result<(), String> {
-    let out_file = File::create(output_path);
-    let mut out_file = match out_file {
-        Ok(file) => file,
-        Err(_) => return Err(String::from("Cannot create output file.")),
-    };
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A red square on white, with an optional transparent border
+    fn square_image(size: usize, border: usize) -> ColorImage {
+        let mut img = ColorImage::new_w_h(size, size);
+        for y in 0..size {
+            for x in 0..size {
+                let inside = |v: usize| (border..size - border).contains(&v);
+                let color = if !inside(x) || !inside(y) {
+                    Color::new_rgba(0, 0, 0, 0)
+                } else if (size / 4..size * 3 / 4).contains(&x)
+                    && (size / 4..size * 3 / 4).contains(&y)
+                {
+                    Color::new(255, 0, 0)
+                } else {
+                    Color::new(255, 255, 255)
+                };
+                img.set_pixel(x, y, &color);
+            }
+        }
+        img
+    }
+
+    fn configs() -> Vec<Config> {
+        [Hierarchical::Stacked, Hierarchical::Cutout]
+            .into_iter()
+            .map(|hierarchical| Config {
+                hierarchical,
+                ..Config::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn converts_empty_and_fully_transparent_images() {
+        for config in configs() {
+            for (width, height) in [(0, 0), (0, 8), (32, 32)] {
+                // New images are fully transparent
+                let img = ColorImage::new_w_h(width, height);
+                let svg = convert(img.clone(), config.clone()).unwrap();
+                assert!(svg.paths.is_empty());
+                assert_eq!((svg.width, svg.height), (width, height));
+
+                let mut reported = vec![];
+                let svg = convert_with_progress(img, config.clone(), |progress| {
+                    reported.push(progress);
+                    ControlFlow::Continue(())
+                })
+                .unwrap();
+                assert!(svg.paths.is_empty());
+                assert_eq!(reported.last().unwrap().stage, Stage::Done);
+            }
+        }
+    }
+
+    #[test]
+    fn progress_never_goes_backwards_and_ends_done() {
+        for config in configs() {
+            let mut reported = vec![];
+            let svg = convert_with_progress(square_image(32, 4), config, |progress| {
+                reported.push(progress);
+                ControlFlow::Continue(())
+            })
+            .unwrap();
+            assert!(!svg.paths.is_empty());
+            assert_eq!(
+                reported[0],
+                Progress {
+                    stage: Stage::Clustering,
+                    percent: 0
+                }
+            );
+            assert!(reported
+                .windows(2)
+                .all(|pair| pair[0].percent <= pair[1].percent));
+            assert_eq!(
+                *reported.last().unwrap(),
+                Progress {
+                    stage: Stage::Done,
+                    percent: 100
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn key_color_skips_the_colors_in_use() {
+        let special_colors = [
+            Color::new(255, 0, 0),
+            Color::new(0, 255, 0),
+            Color::new(0, 0, 255),
+            Color::new(255, 255, 0),
+            Color::new(0, 255, 255),
+            Color::new(255, 0, 255),
+        ];
+        let mut img = ColorImage::new_w_h(special_colors.len() + 1, 1);
+        for (x, color) in special_colors.iter().enumerate() {
+            img.set_pixel(x, 0, color);
+        }
+        // Black is never a key, as it means no keying
+        assert_eq!(
+            find_unused_color_in_image(&img).unwrap(),
+            Color::new(0, 0, 1)
+        );
+
+        img.set_pixel(special_colors.len(), 0, &Color::new(0, 0, 1));
+        assert_eq!(
+            find_unused_color_in_image(&img).unwrap(),
+            Color::new(0, 0, 2)
+        );
+    }
+
+    #[test]
+    fn keyed_images_convert_the_same_every_time() {
+        let img = square_image(32, 4);
+        let key = key_image(&mut img.clone()).unwrap();
+        assert_ne!(key, Color::default());
+        assert_eq!(key_image(&mut img.clone()).unwrap(), key);
+        for config in configs() {
+            let first = convert(img.clone(), config.clone()).unwrap().to_string();
+            let second = convert(img.clone(), config).unwrap().to_string();
+            assert_eq!(first, second);
+        }
+    }
 
-    write!(&mut out_file, "{}", svg).expect("failed to write file.");
+    #[test]
+    fn rejects_an_invalid_config() {
+        let config = Config {
+            color_precision: 0,
+            ..Config::default()
+        };
+        let result = convert(square_image(16, 0), config);
+        assert!(matches!(result, Err(VtracerError::InvalidConfig(fields)) if fields.len() == 1));
+    }
 
-    Ok(())
+    #[test]
+    fn breaking_cancels_the_conversion() {
+        for stop_after in [0, 1, 3] {
+            let mut calls = 0;
+            let result = convert_with_progress(square_image(32, 0), Config::default(), |_| {
+                calls += 1;
+                if calls > stop_after {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            });
+            assert!(matches!(result, Err(VtracerError::Cancelled)));
+            assert_eq!(calls, stop_after + 1);
+        }
+    }
 }
@@ -0,0 +1,431 @@
+use std::fmt::Display;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use visioncortex::{Color, PointF64};
+
+use crate::error::InvalidField;
+use crate::segments::{subpaths, Segment};
+use crate::svg::SvgFile;
+
+/// Drawing units of a DXF file, stored as `$INSUNITS` in the header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DxfUnits {
+    /// One drawing unit per pixel
+    Unitless,
+    Inches,
+    Millimeters,
+    Centimeters,
+    Meters,
+}
+
+impl DxfUnits {
+    fn insunits(self) -> u8 {
+        match self {
+            Self::Unitless => 0,
+            Self::Inches => 1,
+            Self::Millimeters => 4,
+            Self::Centimeters => 5,
+            Self::Meters => 6,
+        }
+    }
+
+    fn per_inch(self) -> Option<f64> {
+        match self {
+            Self::Unitless => None,
+            Self::Inches => Some(1.0),
+            Self::Millimeters => Some(25.4),
+            Self::Centimeters => Some(2.54),
+            Self::Meters => Some(0.0254),
+        }
+    }
+}
+
+impl FromStr for DxfUnits {
+    type Err = InvalidField;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "px" | "unitless" => Ok(Self::Unitless),
+            "in" => Ok(Self::Inches),
+            "mm" => Ok(Self::Millimeters),
+            "cm" => Ok(Self::Centimeters),
+            "m" => Ok(Self::Meters),
+            _ => Err(InvalidField::new(
+                "units",
+                s,
+                "`px`, `in`, `mm`, `cm` or `m`",
+            )),
+        }
+    }
+}
+
+/// Options for `SvgFile::write_dxf`
+#[derive(Debug, Clone)]
+pub struct DxfOptions {
+    pub units: DxfUnits,
+    /// Resolution of the image, used to convert pixels into `units`. Ignored when unitless.
+    pub dpi: f64,
+}
+
+impl Default for DxfOptions {
+    fn default() -> Self {
+        Self {
+            units: DxfUnits::Unitless,
+            dpi: 96.0,
+        }
+    }
+}
+
+impl SvgFile {
+    /// Write a DXF (AutoCAD 2004) drawing. Every subpath becomes a closed `LWPOLYLINE`, or a
+    /// `SPLINE` if it has curves, on a layer named after its fill color. The y axis points up.
+    pub fn write_dxf<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        options: &DxfOptions,
+    ) -> io::Result<()> {
+        let scale = options
+            .units
+            .per_inch()
+            .map_or(1.0, |per_inch| per_inch / options.dpi);
+        let height = self.height as f64;
+        let to_cad = |p: PointF64| PointF64::new(p.x * scale, (height - p.y) * scale);
+
+        let mut layers: Vec<String> = vec![];
+        let mut entities = vec![];
+        for path in &self.paths {
+            let layer = layer_name(&path.color);
+            if !layers.contains(&layer) {
+                layers.push(layer.clone());
+            }
+            for subpath in subpaths(&path.path) {
+                entities.push((layer.clone(), subpath));
+            }
+        }
+
+        // Handles are numbered in order of writing, so the sections go into a buffer
+        // to learn the next free handle for the header
+        let mut dxf = DxfWriter {
+            inner: vec![],
+            next_handle: 1,
+        };
+
+        dxf.section("TABLES")?;
+        dxf.empty_table("VPORT")?;
+
+        let table = dxf.begin_table("LTYPE")?;
+        for name in ["ByBlock", "ByLayer", "Continuous"] {
+            let description = if name == "Continuous" {
+                "Solid line"
+            } else {
+                ""
+            };
+            dxf.table_entry("LTYPE", &table, "AcDbLinetypeTableRecord", name)?;
+            dxf.pair(3, description)?;
+            dxf.pair(72, 65)?;
+            dxf.pair(73, 0)?;
+            dxf.pair(40, 0.0)?;
+        }
+        dxf.end_table()?;
+
+        let table = dxf.begin_table("LAYER")?;
+        for name in std::iter::once("0").chain(layers.iter().map(String::as_str)) {
+            dxf.table_entry("LAYER", &table, "AcDbLayerTableRecord", name)?;
+            dxf.pair(62, 7)?;
+            dxf.pair(6, "Continuous")?;
+            if let Some(rgb) = name.strip_prefix("COLOR_") {
+                dxf.pair(420, u32::from_str_radix(rgb, 16).unwrap_or_default())?;
+            }
+        }
+        dxf.end_table()?;
+
+        let table = dxf.begin_table("STYLE")?;
+        dxf.table_entry("STYLE", &table, "AcDbTextStyleTableRecord", "Standard")?;
+        dxf.pair(40, 0.0)?;
+        dxf.pair(41, 1.0)?;
+        dxf.pair(50, 0.0)?;
+        dxf.pair(71, 0)?;
+        dxf.pair(42, 2.5)?;
+        dxf.pair(3, "txt")?;
+        dxf.pair(4, "")?;
+        dxf.end_table()?;
+
+        dxf.empty_table("VIEW")?;
+        dxf.empty_table("UCS")?;
+
+        let table = dxf.begin_table("APPID")?;
+        dxf.table_entry("APPID", &table, "AcDbRegAppTableRecord", "ACAD")?;
+        dxf.end_table()?;
+
+        dxf.begin_table("DIMSTYLE")?;
+        dxf.pair(100, "AcDbDimStyleTable")?;
+        dxf.end_table()?;
+
+        let table = dxf.begin_table("BLOCK_RECORD")?;
+        let model_space = dxf.table_entry(
+            "BLOCK_RECORD",
+            &table,
+            "AcDbBlockTableRecord",
+            "*Model_Space",
+        )?;
+        let paper_space = dxf.table_entry(
+            "BLOCK_RECORD",
+            &table,
+            "AcDbBlockTableRecord",
+            "*Paper_Space",
+        )?;
+        dxf.end_table()?;
+        dxf.end_section()?;
+
+        dxf.section("BLOCKS")?;
+        dxf.block(&model_space, "*Model_Space", false)?;
+        dxf.block(&paper_space, "*Paper_Space", true)?;
+        dxf.end_section()?;
+
+        dxf.section("ENTITIES")?;
+        for (layer, subpath) in &entities {
+            let is_curved = subpath
+                .iter()
+                .any(|segment| matches!(segment, Segment::Cubic(..)));
+            if is_curved {
+                dxf.spline(&model_space, layer, subpath, to_cad)?;
+            } else {
+                dxf.polyline(&model_space, layer, subpath, to_cad)?;
+            }
+        }
+        dxf.end_section()?;
+
+        dxf.section("OBJECTS")?;
+        let root = dxf.handle();
+        let group = dxf.handle();
+        dxf.pair(0, "DICTIONARY")?;
+        dxf.pair(5, &root)?;
+        dxf.pair(330, 0)?;
+        dxf.pair(100, "AcDbDictionary")?;
+        dxf.pair(281, 1)?;
+        dxf.pair(3, "ACAD_GROUP")?;
+        dxf.pair(350, &group)?;
+        dxf.pair(0, "DICTIONARY")?;
+        dxf.pair(5, &group)?;
+        dxf.pair(330, &root)?;
+        dxf.pair(100, "AcDbDictionary")?;
+        dxf.pair(281, 1)?;
+        dxf.end_section()?;
+        dxf.pair(0, "EOF")?;
+
+        let body = dxf.inner;
+        let mut dxf = DxfWriter {
+            inner: writer,
+            next_handle: dxf.next_handle,
+        };
+        dxf.section("HEADER")?;
+        dxf.pair(9, "$ACADVER")?;
+        dxf.pair(1, "AC1018")?;
+        dxf.pair(9, "$HANDSEED")?;
+        dxf.pair(5, format!("{:X}", dxf.next_handle))?;
+        dxf.pair(9, "$INSUNITS")?;
+        dxf.pair(70, options.units.insunits())?;
+        dxf.pair(9, "$EXTMIN")?;
+        dxf.point(10, PointF64::new(0.0, 0.0))?;
+        dxf.pair(9, "$EXTMAX")?;
+        dxf.point(10, PointF64::new(self.width as f64 * scale, height * scale))?;
+        dxf.end_section()?;
+        dxf.inner.write_all(&body)
+    }
+}
+
+fn layer_name(color: &Color) -> String {
+    format!("COLOR_{:02X}{:02X}{:02X}", color.r, color.g, color.b)
+}
+
+/// Writes group code / value pairs and hands out entity handles
+struct DxfWriter<W> {
+    inner: W,
+    next_handle: u32,
+}
+
+impl<W: Write> DxfWriter<W> {
+    fn pair(&mut self, code: u16, value: impl Display) -> io::Result<()> {
+        writeln!(self.inner, "{:>3}\n{}", code, value)
+    }
+
+    fn point(&mut self, code: u16, point: PointF64) -> io::Result<()> {
+        self.pair(code, point.x)?;
+        self.pair(code + 10, point.y)?;
+        self.pair(code + 20, 0.0)
+    }
+
+    fn handle(&mut self) -> String {
+        let handle = format!("{:X}", self.next_handle);
+        self.next_handle += 1;
+        handle
+    }
+
+    fn section(&mut self, name: &str) -> io::Result<()> {
+        self.pair(0, "SECTION")?;
+        self.pair(2, name)
+    }
+
+    fn end_section(&mut self) -> io::Result<()> {
+        self.pair(0, "ENDSEC")
+    }
+
+    fn begin_table(&mut self, name: &str) -> io::Result<String> {
+        let handle = self.handle();
+        self.pair(0, "TABLE")?;
+        self.pair(2, name)?;
+        self.pair(5, &handle)?;
+        self.pair(330, 0)?;
+        self.pair(100, "AcDbSymbolTable")?;
+        self.pair(70, 0)?;
+        Ok(handle)
+    }
+
+    fn end_table(&mut self) -> io::Result<()> {
+        self.pair(0, "ENDTAB")
+    }
+
+    fn empty_table(&mut self, name: &str) -> io::Result<()> {
+        self.begin_table(name)?;
+        self.end_table()
+    }
+
+    fn table_entry(
+        &mut self,
+        kind: &str,
+        table: &str,
+        subclass: &str,
+        name: &str,
+    ) -> io::Result<String> {
+        let handle = self.handle();
+        self.pair(0, kind)?;
+        self.pair(5, &handle)?;
+        self.pair(330, table)?;
+        self.pair(100, "AcDbSymbolTableRecord")?;
+        self.pair(100, subclass)?;
+        self.pair(2, name)?;
+        self.pair(70, 0)?;
+        Ok(handle)
+    }
+
+    fn block(&mut self, record: &str, name: &str, paper_space: bool) -> io::Result<()> {
+        let handle = self.handle();
+        self.entity("BLOCK", &handle, record, "0", paper_space)?;
+        self.pair(100, "AcDbBlockBegin")?;
+        self.pair(2, name)?;
+        self.pair(70, 0)?;
+        self.point(10, PointF64::new(0.0, 0.0))?;
+        self.pair(3, name)?;
+        self.pair(1, "")?;
+        let handle = self.handle();
+        self.entity("ENDBLK", &handle, record, "0", paper_space)?;
+        self.pair(100, "AcDbBlockEnd")
+    }
+
+    fn entity(
+        &mut self,
+        kind: &str,
+        handle: &str,
+        owner: &str,
+        layer: &str,
+        paper_space: bool,
+    ) -> io::Result<()> {
+        self.pair(0, kind)?;
+        self.pair(5, handle)?;
+        self.pair(330, owner)?;
+        self.pair(100, "AcDbEntity")?;
+        if paper_space {
+            self.pair(67, 1)?;
+        }
+        self.pair(8, layer)
+    }
+
+    fn polyline<F>(
+        &mut self,
+        owner: &str,
+        layer: &str,
+        subpath: &[Segment],
+        to_cad: F,
+    ) -> io::Result<()>
+    where
+        F: Fn(PointF64) -> PointF64,
+    {
+        let handle = self.handle();
+        self.entity("LWPOLYLINE", &handle, owner, layer, false)?;
+        self.pair(100, "AcDbPolyline")?;
+        self.pair(90, subpath.len())?;
+        // Closed
+        self.pair(70, 1)?;
+        self.pair(43, 0.0)?;
+        for segment in subpath {
+            if let Segment::Move(p) | Segment::Line(p) = *segment {
+                let p = to_cad(p);
+                self.pair(10, p.x)?;
+                self.pair(20, p.y)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write a subpath as a clamped cubic B-spline, which passes through the control points
+    /// of a chain of beziers when every inner knot has a multiplicity of 3
+    fn spline<F>(
+        &mut self,
+        owner: &str,
+        layer: &str,
+        subpath: &[Segment],
+        to_cad: F,
+    ) -> io::Result<()>
+    where
+        F: Fn(PointF64) -> PointF64,
+    {
+        let mut control_points = vec![];
+        let mut current = PointF64::new(0.0, 0.0);
+        for segment in subpath {
+            match *segment {
+                Segment::Move(p) => control_points.push(p),
+                Segment::Line(p) => control_points.extend(line_as_cubic(current, p)),
+                Segment::Cubic(a, b, c) => control_points.extend([a, b, c]),
+            }
+            current = *control_points.last().unwrap();
+        }
+        if let Some(&start) = control_points.first() {
+            if current != start {
+                control_points.extend(line_as_cubic(current, start));
+            }
+        }
+        let curves = (control_points.len() - 1) / 3;
+
+        let handle = self.handle();
+        self.entity("SPLINE", &handle, owner, layer, false)?;
+        self.pair(100, "AcDbSpline")?;
+        self.pair(210, 0.0)?;
+        self.pair(220, 0.0)?;
+        self.pair(230, 1.0)?;
+        // Planar
+        self.pair(70, 8)?;
+        self.pair(71, 3)?;
+        self.pair(72, 3 * curves + 5)?;
+        self.pair(73, control_points.len())?;
+        self.pair(74, 0)?;
+        self.pair(42, 0.0000001)?;
+        self.pair(43, 0.0000001)?;
+        for knot in 0..=curves {
+            let multiplicity = if knot == 0 || knot == curves { 4 } else { 3 };
+            for _ in 0..multiplicity {
+                self.pair(40, knot)?;
+            }
+        }
+        for p in control_points {
+            self.point(10, to_cad(p))?;
+        }
+        Ok(())
+    }
+}
+
+/// Control points of a straight bezier from `a` to `b`
+fn line_as_cubic(a: PointF64, b: PointF64) -> [PointF64; 3] {
+    let lerp = |t: f64| PointF64::new(a.x + (b.x - a.x) * t, a.y + (b.y - a.y) * t);
+    [lerp(1.0 / 3.0), lerp(2.0 / 3.0), b]
+}
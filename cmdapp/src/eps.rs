@@ -0,0 +1,54 @@
+use std::io::{self, Write};
+
+use crate::segments::{format_number, write_postfix_path, PostfixOperators};
+use crate::svg::SvgFile;
+
+const POSTSCRIPT_OPERATORS: PostfixOperators = PostfixOperators {
+    move_to: "moveto",
+    line_to: "lineto",
+    curve_to: "curveto",
+    close_path: "closepath",
+};
+
+impl SvgFile {
+    /// Write an Encapsulated PostScript file, one pixel per point. Paths are filled in the same
+    /// order as in the svg, so later paths are painted over earlier ones.
+    pub fn write_eps<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        writeln!(writer, "%!PS-Adobe-3.0 EPSF-3.0")?;
+        writeln!(
+            writer,
+            "%%Creator: visioncortex VTracer {}",
+            env!("CARGO_PKG_VERSION")
+        )?;
+        writeln!(writer, "%%BoundingBox: 0 0 {} {}", self.width, self.height)?;
+        writeln!(writer, "%%LanguageLevel: 2")?;
+        writeln!(writer, "%%EndComments")?;
+        writeln!(writer, "gsave")?;
+        // Flip the y axis, so that paths are drawn in pixel coordinates
+        writeln!(writer, "0 {} translate", self.height)?;
+        writeln!(writer, "1 -1 scale")?;
+        for path in &self.paths {
+            if path.path.paths.is_empty() {
+                continue;
+            }
+            writeln!(
+                writer,
+                "{} {} {} setrgbcolor",
+                format_number(path.color.r as f64 / 255.0, Some(3)),
+                format_number(path.color.g as f64 / 255.0, Some(3)),
+                format_number(path.color.b as f64 / 255.0, Some(3))
+            )?;
+            writeln!(writer, "newpath")?;
+            write_postfix_path(
+                writer,
+                &path.path,
+                self.path_precision,
+                &POSTSCRIPT_OPERATORS,
+            )?;
+            writeln!(writer, "fill")?;
+        }
+        writeln!(writer, "grestore")?;
+        writeln!(writer, "showpage")?;
+        writeln!(writer, "%%EOF")
+    }
+}
@@ -0,0 +1,111 @@
+use std::path::PathBuf;
+use std::{error::Error, fmt, io};
+
+use image::ImageError;
+
+/// Errors that can occur while converting an image into vector graphics
+#[derive(Debug)]
+pub enum VtracerError {
+    /// The input could not be decoded as an image
+    Decode(ImageError),
+    /// Reading the input or writing the output failed
+    Io(io::Error),
+    /// No color absent from the image could be found to key out transparent pixels
+    Keying,
+    /// A config file is not valid TOML or JSON, or does not match the `Config` schema
+    ConfigParse(Box<dyn Error + Send + Sync>),
+    /// The config contains values the converter cannot work with, listing every offending field
+    InvalidConfig(Vec<InvalidField>),
+    /// The conversion was cancelled by its progress callback
+    Cancelled,
+    /// The world file next to the input is not six numbers, listing every problem with it
+    WorldFile(PathBuf, Vec<String>),
+}
+
+/// A config field holding a value outside of what the converter accepts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidField {
+    /// Name of the field, as spelled in `Config`
+    pub field: &'static str,
+    /// The rejected value
+    pub value: String,
+    /// Description of the accepted values
+    pub expected: &'static str,
+}
+
+impl InvalidField {
+    pub fn new(field: &'static str, value: impl ToString, expected: &'static str) -> Self {
+        Self {
+            field,
+            value: value.to_string(),
+            expected,
+        }
+    }
+}
+
+impl fmt::Display for InvalidField {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} is `{}`, expected {}",
+            self.field, self.value, self.expected
+        )
+    }
+}
+
+impl Error for InvalidField {}
+
+impl fmt::Display for VtracerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Decode(err) => write!(f, "failed to decode image: {}", err),
+            Self::Io(err) => write!(f, "I/O error: {}", err),
+            Self::Keying => write!(f, "unable to find unused color in image to use as key"),
+            Self::ConfigParse(err) => write!(f, "failed to parse config: {}", err),
+            Self::InvalidConfig(fields) => {
+                write!(f, "invalid config: ")?;
+                for (i, field) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "; ")?;
+                    }
+                    write!(f, "{}", field)?;
+                }
+                Ok(())
+            }
+            Self::Cancelled => write!(f, "conversion cancelled"),
+            Self::WorldFile(path, problems) => write!(
+                f,
+                "invalid world file {}: {}",
+                path.display(),
+                problems.join("; ")
+            ),
+        }
+    }
+}
+
+impl Error for VtracerError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Decode(err) => Some(err),
+            Self::Io(err) => Some(err),
+            Self::ConfigParse(err) => Some(err.as_ref()),
+            Self::Keying | Self::InvalidConfig(_) | Self::Cancelled | Self::WorldFile(..) => None,
+        }
+    }
+}
+
+impl From<io::Error> for VtracerError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<ImageError> for VtracerError {
+    fn from(err: ImageError) -> Self {
+        match err {
+            // A missing or unreadable file is not a problem with the image data itself
+            ImageError::IoError(err) => Self::Io(err),
+            err => Self::Decode(err),
+        }
+    }
+}
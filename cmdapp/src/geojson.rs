@@ -0,0 +1,280 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use visioncortex::PointF64;
+
+use crate::error::VtracerError;
+use crate::segments::{flatten, subpaths};
+use crate::svg::SvgFile;
+
+/// The affine transform of an ESRI world file, from pixel to map coordinates
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WorldFile {
+    /// Size of a pixel in x
+    pub a: f64,
+    /// Rotation term of y
+    pub d: f64,
+    /// Rotation term of x
+    pub b: f64,
+    /// Size of a pixel in y, usually negative
+    pub e: f64,
+    /// X of the center of the top left pixel
+    pub c: f64,
+    /// Y of the center of the top left pixel
+    pub f: f64,
+}
+
+impl WorldFile {
+    /// Parse the six lines of a world file, in the order `a d b e c f`, describing every
+    /// problem with it on failure
+    pub fn parse(s: &str) -> Result<Self, Vec<String>> {
+        let lines: Vec<&str> = s.split_whitespace().collect();
+        let mut values = vec![];
+        let mut problems = vec![];
+        for line in &lines {
+            match line.parse::<f64>() {
+                Ok(value) => values.push(value),
+                Err(_) => problems.push(format!("`{}` is not a number", line)),
+            }
+        }
+        if lines.len() != 6 {
+            problems.push(format!("{} lines long, expected 6", lines.len()));
+        }
+        match values[..] {
+            [a, d, b, e, c, f] if problems.is_empty() => Ok(Self { a, d, b, e, c, f }),
+            _ => Err(problems),
+        }
+    }
+
+    /// Read the world file of the image at `image_path`, if there is one next to it.
+    /// The world file of `map.png` is `map.pgw`, `map.pngw` or `map.wld`.
+    pub fn find(image_path: &Path) -> Result<Option<Self>, VtracerError> {
+        let path = match Self::candidates(image_path)
+            .into_iter()
+            .find(|path| path.is_file())
+        {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+        match Self::parse(&fs::read_to_string(&path)?) {
+            Ok(world_file) => Ok(Some(world_file)),
+            Err(problems) => Err(VtracerError::WorldFile(path, problems)),
+        }
+    }
+
+    fn candidates(image_path: &Path) -> Vec<PathBuf> {
+        let extension = image_path
+            .extension()
+            .map(|ext| ext.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let mut extensions = vec![];
+        if let (Some(first), Some(last)) = (extension.chars().next(), extension.chars().last()) {
+            extensions.push(format!("{}{}w", first, last));
+            extensions.push(format!("{}w", extension));
+        }
+        extensions.push("wld".to_owned());
+        extensions
+            .into_iter()
+            .map(|ext| image_path.with_extension(ext))
+            .collect()
+    }
+
+    /// Map a point in pixels, where the top left pixel spans 0 to 1, to map coordinates
+    pub fn transform(&self, p: PointF64) -> PointF64 {
+        // The world file refers to pixel centers
+        let (x, y) = (p.x - 0.5, p.y - 0.5);
+        PointF64::new(
+            self.a * x + self.b * y + self.c,
+            self.d * x + self.e * y + self.f,
+        )
+    }
+}
+
+/// Options for `SvgFile::write_geojson`
+#[derive(Debug, Clone)]
+pub struct GeoJsonOptions {
+    /// Largest distance in pixels between a curve and the straight lines that replace it
+    pub tolerance: f64,
+    /// Maps pixels to map coordinates. Without it, coordinates are pixels with y pointing up.
+    pub world_file: Option<WorldFile>,
+}
+
+impl Default for GeoJsonOptions {
+    fn default() -> Self {
+        Self {
+            tolerance: 0.5,
+            world_file: None,
+        }
+    }
+}
+
+/// A ring is a closed list of points, the first being repeated at the end
+type Ring = Vec<PointF64>;
+
+impl SvgFile {
+    /// Write a GeoJSON FeatureCollection with a Polygon or MultiPolygon Feature per path, in
+    /// drawing order. The fill color is the `color` property, as `#RRGGBB`.
+    pub fn write_geojson<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        options: &GeoJsonOptions,
+    ) -> io::Result<()> {
+        let height = self.height as f64;
+        let transform = |p: PointF64| match &options.world_file {
+            Some(world_file) => world_file.transform(p),
+            None => PointF64::new(p.x, height - p.y),
+        };
+
+        write!(writer, r#"{{"type":"FeatureCollection","features":["#)?;
+        let mut is_first = true;
+        for path in &self.paths {
+            let rings: Vec<Ring> = subpaths(&path.path)
+                .iter()
+                .map(|subpath| flatten(subpath, options.tolerance))
+                .filter(|ring| ring.len() >= 4)
+                .collect();
+            let polygons: Vec<Vec<Ring>> = polygons(rings)
+                .into_iter()
+                .map(|polygon| {
+                    polygon
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, ring)| {
+                            let mut ring: Ring = ring.into_iter().map(transform).collect();
+                            // The right hand rule: exteriors are counterclockwise, holes clockwise
+                            if (signed_area(&ring) > 0.0) != (i == 0) {
+                                ring.reverse();
+                            }
+                            ring
+                        })
+                        .collect()
+                })
+                .collect();
+            if polygons.is_empty() {
+                continue;
+            }
+
+            writeln!(writer, "{}", if is_first { "" } else { "," })?;
+            is_first = false;
+            write!(
+                writer,
+                r#"{{"type":"Feature","properties":{{"color":"{}"}},"geometry":"#,
+                path.color.to_hex_string()
+            )?;
+            if let [polygon] = &polygons[..] {
+                write!(writer, r#"{{"type":"Polygon","coordinates":"#)?;
+                write_polygon(writer, polygon)?;
+            } else {
+                write!(writer, r#"{{"type":"MultiPolygon","coordinates":["#)?;
+                for (i, polygon) in polygons.iter().enumerate() {
+                    if i > 0 {
+                        write!(writer, ",")?;
+                    }
+                    write_polygon(writer, polygon)?;
+                }
+                write!(writer, "]")?;
+            }
+            write!(writer, "}}}}")?;
+        }
+        writeln!(writer, "\n]}}")
+    }
+}
+
+fn write_polygon<W: Write + ?Sized>(writer: &mut W, rings: &[Ring]) -> io::Result<()> {
+    write!(writer, "[")?;
+    for (i, ring) in rings.iter().enumerate() {
+        if i > 0 {
+            write!(writer, ",")?;
+        }
+        write!(writer, "[")?;
+        for (j, p) in ring.iter().enumerate() {
+            if j > 0 {
+                write!(writer, ",")?;
+            }
+            write!(writer, "[{},{}]", p.x, p.y)?;
+        }
+        write!(writer, "]")?;
+    }
+    write!(writer, "]")
+}
+
+/// Group the rings of a path into polygons, each an exterior followed by its holes.
+/// Holes wind opposite to the outermost ring, as the svg is filled by the nonzero rule.
+fn polygons(rings: Vec<Ring>) -> Vec<Vec<Ring>> {
+    let areas: Vec<f64> = rings.iter().map(|ring| signed_area(ring)).collect();
+    let outer_sign = match areas.iter().max_by(|a, b| a.abs().total_cmp(&b.abs())) {
+        Some(area) => area.signum(),
+        None => return vec![],
+    };
+
+    let mut polygons: Vec<Vec<Ring>> = vec![];
+    let mut exterior_areas = vec![];
+    let mut holes = vec![];
+    for (ring, area) in rings.into_iter().zip(areas) {
+        if area.signum() == outer_sign {
+            polygons.push(vec![ring]);
+            exterior_areas.push(area.abs());
+        } else {
+            holes.push(ring);
+        }
+    }
+
+    for hole in holes {
+        // The smallest exterior enclosing most of the hole's points, as they may touch its edge
+        let parent = polygons
+            .iter()
+            .enumerate()
+            .filter(|(_, polygon)| {
+                let inside = hole.iter().filter(|&&p| contains(&polygon[0], p)).count();
+                2 * inside >= hole.len()
+            })
+            .min_by(|a, b| exterior_areas[a.0].total_cmp(&exterior_areas[b.0]))
+            .map(|(i, _)| i)
+            .or_else(|| {
+                (0..polygons.len()).max_by(|&a, &b| exterior_areas[a].total_cmp(&exterior_areas[b]))
+            });
+        if let Some(parent) = parent {
+            polygons[parent].push(hole);
+        }
+    }
+    polygons
+}
+
+/// Twice the area enclosed by `ring`, positive if it is counterclockwise in a y up system
+fn signed_area(ring: &[PointF64]) -> f64 {
+    ring.windows(2)
+        .map(|w| w[0].x * w[1].y - w[1].x * w[0].y)
+        .sum()
+}
+
+/// Whether `p` is inside `ring`, by casting a ray towards positive x
+fn contains(ring: &[PointF64], p: PointF64) -> bool {
+    let mut inside = false;
+    for w in ring.windows(2) {
+        let (a, b) = (w[0], w[1]);
+        if (a.y > p.y) != (b.y > p.y) && p.x < a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn world_file_parsing_reports_every_problem() {
+        let world_file = WorldFile::parse("0.5\n0\n0\n-0.5\n100.25\n200.75\n").unwrap();
+        assert_eq!(
+            world_file.transform(PointF64::new(0.5, 0.5)),
+            PointF64::new(100.25, 200.75)
+        );
+
+        assert_eq!(
+            WorldFile::parse("0.5\nx\n0\n-0.5\n100\n").unwrap_err(),
+            ["`x` is not a number", "5 lines long, expected 6"]
+        );
+    }
+}
@@ -0,0 +1,277 @@
+use std::io::{self, Read, Write};
+
+use serde::de::Error as _;
+use serde::{Deserialize, Serialize};
+use visioncortex::{
+    Color, CompoundPath, CompoundPathElement, PathF64, PathI32, PointF64, PointI32, Spline,
+};
+
+use crate::svg::{PathMetadata, SvgFile, SvgPath};
+
+const JSON_VERSION: u32 = 1;
+
+#[derive(Deserialize)]
+struct JsonFile {
+    version: u32,
+    width: usize,
+    height: usize,
+    path_precision: Option<u32>,
+    paths: Vec<JsonPath>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonPath {
+    #[serde(with = "ColorDef")]
+    color: Color,
+    #[serde(default, skip_deserializing)]
+    offset: [f64; 2],
+    #[serde(default)]
+    metadata: PathMetadata,
+    subpaths: Vec<JsonSubpath>,
+}
+
+/// Mirror of `Color` for serde
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Color")]
+struct ColorDef {
+    r: u8,
+    g: u8,
+    b: u8,
+    a: u8,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonSubpath {
+    kind: SubpathKind,
+    segments: Vec<JsonSegment>,
+}
+
+/// Which `CompoundPathElement` a subpath is
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum SubpathKind {
+    Integer,
+    Float,
+    Spline,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum JsonSegment {
+    Move([f64; 2]),
+    Line([f64; 2]),
+    Cubic([[f64; 2]; 3]),
+}
+
+impl SvgFile {
+    /// Write a lossless JSON representation, for storing traces and processing them with other
+    /// tools. It is a single object:
+    ///
+    /// ```json
+    /// {"version":1,"width":100,"height":80,"path_precision":2,"paths":[
+    /// {"color":{"r":255,"g":0,"b":0,"a":255},"offset":[10.0,10.0],
+    ///  "metadata":{"area":1200,"rect":{"left":10,"top":10,"right":50,"bottom":40},"cluster_index":3,"depth":1},
+    ///  "subpaths":[{"kind":"integer","segments":[{"move":[10.0,10.0]},{"line":[50.0,10.0]},{"line":[50.0,40.0]}]}]}
+    /// ]}
+    /// ```
+    ///
+    /// - `path_precision` is the number of decimal places of svg output, or `null` for no rounding.
+    /// - `paths` are in drawing order, each path on its own line to keep diffs readable.
+    /// - `offset` is the `translate` the svg writer gives the path, which is its first point.
+    ///   It is informational and ignored when reading.
+    /// - `metadata` is what clustering knew about the path, see `PathMetadata`. It may be left out.
+    /// - `subpaths` are closed. Their `kind` is `integer` or `float` for polygons, which consist of
+    ///   a `move` followed by `line`s, or `spline` for a `move` followed by `cubic` beziers given as
+    ///   `[control1, control2, end]`.
+    ///
+    /// Coordinates are absolute pixel coordinates, with the origin at the top left of the image.
+    pub fn write_json<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        write!(
+            writer,
+            r#"{{"version":{},"width":{},"height":{},"path_precision":{},"paths":["#,
+            JSON_VERSION,
+            self.width,
+            self.height,
+            serde_json::to_string(&self.path_precision)?
+        )?;
+        for (i, path) in self.paths.iter().enumerate() {
+            writeln!(writer, "{}", if i == 0 { "" } else { "," })?;
+            serde_json::to_writer(&mut *writer, &JsonPath::from(path))?;
+        }
+        writeln!(writer, "\n]}}")
+    }
+
+    /// Read back the JSON representation written by `write_json`
+    pub fn read_json<R: Read>(reader: R) -> serde_json::Result<Self> {
+        let file: JsonFile = serde_json::from_reader(reader)?;
+        if file.version != JSON_VERSION {
+            return Err(serde_json::Error::custom(format!(
+                "unsupported version {}, expected {}",
+                file.version, JSON_VERSION
+            )));
+        }
+        let mut svg = SvgFile::new(file.width, file.height, file.path_precision);
+        for path in file.paths {
+            let mut compound = CompoundPath::new();
+            for subpath in path.subpaths {
+                compound
+                    .paths
+                    .push(subpath.into_element().map_err(serde_json::Error::custom)?);
+            }
+            svg.add_path_with_metadata(compound, path.color, path.metadata);
+        }
+        Ok(svg)
+    }
+}
+
+impl From<&SvgPath> for JsonPath {
+    fn from(path: &SvgPath) -> Self {
+        let subpaths: Vec<JsonSubpath> = path.path.paths.iter().map(JsonSubpath::from).collect();
+        let offset = match subpaths
+            .first()
+            .and_then(|subpath| subpath.segments.first())
+        {
+            Some(JsonSegment::Move(p)) => *p,
+            _ => [0.0, 0.0],
+        };
+        Self {
+            color: path.color,
+            offset,
+            metadata: path.metadata.clone(),
+            subpaths,
+        }
+    }
+}
+
+impl From<&CompoundPathElement> for JsonSubpath {
+    fn from(element: &CompoundPathElement) -> Self {
+        let polygon = |points: Vec<[f64; 2]>| {
+            points
+                .into_iter()
+                .enumerate()
+                .map(|(i, p)| {
+                    if i == 0 {
+                        JsonSegment::Move(p)
+                    } else {
+                        JsonSegment::Line(p)
+                    }
+                })
+                .collect()
+        };
+        match element {
+            CompoundPathElement::PathI32(path) => Self {
+                kind: SubpathKind::Integer,
+                segments: polygon(path.path.iter().map(|p| [p.x as f64, p.y as f64]).collect()),
+            },
+            CompoundPathElement::PathF64(path) => Self {
+                kind: SubpathKind::Float,
+                segments: polygon(path.path.iter().map(|p| [p.x, p.y]).collect()),
+            },
+            CompoundPathElement::Spline(spline) => {
+                let mut segments = vec![];
+                if let Some((first, rest)) = spline.points.split_first() {
+                    segments.push(JsonSegment::Move([first.x, first.y]));
+                    segments.extend(rest.chunks_exact(3).map(|c| {
+                        JsonSegment::Cubic([[c[0].x, c[0].y], [c[1].x, c[1].y], [c[2].x, c[2].y]])
+                    }));
+                }
+                Self {
+                    kind: SubpathKind::Spline,
+                    segments,
+                }
+            }
+        }
+    }
+}
+
+impl JsonSubpath {
+    fn into_element(self) -> Result<CompoundPathElement, String> {
+        let mut points = vec![];
+        for (i, segment) in self.segments.into_iter().enumerate() {
+            match (self.kind, segment) {
+                (_, JsonSegment::Move(p)) if i == 0 => points.push(p),
+                (SubpathKind::Integer | SubpathKind::Float, JsonSegment::Line(p)) if i > 0 => {
+                    points.push(p)
+                }
+                (SubpathKind::Spline, JsonSegment::Cubic(c)) if i > 0 => points.extend(c),
+                _ => {
+                    return Err(format!(
+                        "unexpected segment {} in a subpath of kind {}",
+                        i,
+                        self.kind.name()
+                    ))
+                }
+            }
+        }
+        let point_f64 = |[x, y]: [f64; 2]| PointF64::new(x, y);
+        Ok(match self.kind {
+            SubpathKind::Integer => {
+                let mut path = PathI32::new();
+                for [x, y] in points {
+                    if x.fract() != 0.0 || y.fract() != 0.0 {
+                        return Err(format!(
+                            "non-integer point {},{} in an integer subpath",
+                            x, y
+                        ));
+                    }
+                    path.path.push(PointI32::new(x as i32, y as i32));
+                }
+                CompoundPathElement::PathI32(path)
+            }
+            SubpathKind::Float => {
+                let mut path = PathF64::new();
+                path.path.extend(points.into_iter().map(point_f64));
+                CompoundPathElement::PathF64(path)
+            }
+            SubpathKind::Spline => {
+                let mut spline = Spline::new(PointF64::default());
+                spline.points = points.into_iter().map(point_f64).collect();
+                CompoundPathElement::Spline(spline)
+            }
+        })
+    }
+}
+
+impl SubpathKind {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Integer => "integer",
+            Self::Float => "float",
+            Self::Spline => "spline",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writing_what_was_read_gives_the_same_bytes() {
+        let mut svg = SvgFile::new(64, 48, Some(2));
+        // Values that are only read back exactly with full float precision
+        let points: Vec<PointF64> = (1..40)
+            .map(|i| PointF64::new(13.399999999999999 + i as f64 / 7.0, 0.1 + 0.2 * i as f64))
+            .collect();
+
+        let mut polygon = PathF64::new();
+        for &point in &points {
+            polygon.add(point);
+        }
+        let mut compound = CompoundPath::new();
+        compound.paths.push(CompoundPathElement::PathF64(polygon));
+        compound.paths.push(CompoundPathElement::Spline(Spline {
+            points: points[..7].to_vec(),
+        }));
+        svg.add_path(compound, Color::new_rgba(12, 34, 56, 78));
+
+        let mut first = vec![];
+        svg.write_json(&mut first).unwrap();
+        let mut second = vec![];
+        SvgFile::read_json(&first[..])
+            .unwrap()
+            .write_json(&mut second)
+            .unwrap();
+        assert_eq!(String::from_utf8(first), String::from_utf8(second));
+    }
+}
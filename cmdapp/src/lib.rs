@@ -8,15 +8,37 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+mod android;
 mod config;
 mod converter;
+mod dxf;
+mod eps;
+mod error;
+mod geojson;
+mod json;
+mod merge;
+mod output;
+mod pdf;
+mod plotter;
 #[cfg(feature = "python-binding")]
 mod python;
+mod resolution;
+mod segments;
 mod svg;
+mod tikz;
 
 pub use config::*;
 pub use converter::*;
+pub use dxf::*;
+pub use error::*;
+pub use geojson::*;
+pub use image::{DynamicImage, ImageFormat, RgbaImage};
+pub use output::*;
+pub use pdf::*;
+pub use plotter::*;
 #[cfg(feature = "python-binding")]
 pub use python::*;
+pub use resolution::*;
 pub use svg::*;
+pub use tikz::*;
 pub use visioncortex::ColorImage;
@@ -1,23 +1,159 @@
-mod config;
-mod converter;
-mod svg;
-
-use clap::{App, Arg};
-use config::{ColorMode, Config, Hierarchical, Preset};
-use std::path::PathBuf;
+use clap::{App, Arg, ArgMatches};
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+use std::process;
 use std::str::FromStr;
-use visioncortex::PathSimplifyMode;
+use vtracer::{
+    path_simplify_mode_from_str, read_image_dpi, write_output_file, ColorMode, Config, DxfOptions,
+    DxfUnits, GeoJsonOptions, Hierarchical, InvalidField, OutputFormat, PathCoordinates,
+    PdfOptions, PlotterOptions, Preset, SvgBackground, SvgFile, SvgOptions, SvgUnit, TikzOptions,
+    VtracerError, WorldFile,
+};
+
+/// Settings of the output formats, which do not affect the tracing itself
+#[derive(Default)]
+pub struct OutputOptions {
+    pub pdf: PdfOptions,
+    pub dxf: DxfOptions,
+    pub plotter: PlotterOptions,
+    pub geojson: GeoJsonOptions,
+    pub tikz: TikzOptions,
+    pub svg: SvgOptions,
+    /// Gzip svg output, as it is for the `.svgz` extension
+    pub compress: bool,
+    /// Resolution given on the command line, overriding the one in the metadata of the input
+    pub dpi: Option<f64>,
+    /// Millimeters per pixel of plotter output given on the command line, overriding the dpi
+    pub mm_per_pixel: Option<f64>,
+}
+
+impl OutputOptions {
+    /// Fill in the settings of `format` for converting `input_path` into `output_path`
+    fn apply(
+        &self,
+        format: OutputFormat,
+        input_path: &Path,
+        output_path: &Path,
+    ) -> Result<OutputFormat, VtracerError> {
+        Ok(match format {
+            OutputFormat::Svg(_) if self.compress => OutputFormat::Svgz(self.svg(input_path)?),
+            OutputFormat::Svg(_) => OutputFormat::Svg(self.svg(input_path)?),
+            OutputFormat::Svgz(_) => OutputFormat::Svgz(self.svg(input_path)?),
+            OutputFormat::Pdf(_) => OutputFormat::Pdf(self.pdf(input_path)?),
+            OutputFormat::Dxf(_) => OutputFormat::Dxf(self.dxf(input_path)?),
+            OutputFormat::Gcode(_) => OutputFormat::Gcode(self.plotter(input_path)?),
+            OutputFormat::Hpgl(_) => OutputFormat::Hpgl(self.plotter(input_path)?),
+            OutputFormat::ImageVector { .. } => OutputFormat::ImageVector {
+                name: kotlin_name(output_path),
+            },
+            OutputFormat::GeoJson(_) => OutputFormat::GeoJson(GeoJsonOptions {
+                world_file: WorldFile::find(input_path)?,
+                ..self.geojson.clone()
+            }),
+            OutputFormat::Tikz(_) => OutputFormat::Tikz(self.tikz.clone()),
+            format => format,
+        })
+    }
+
+    /// The resolution of `--dpi`, or else of the metadata of `input_path`, or else 96 dpi.
+    /// Sizing every output by it keeps them at the same physical size.
+    fn dpi(&self, input_path: &Path) -> Result<f64, VtracerError> {
+        match self.dpi {
+            Some(dpi) => Ok(dpi),
+            None => Ok(read_image_dpi(input_path)?.unwrap_or(96.0)),
+        }
+    }
+
+    fn svg(&self, input_path: &Path) -> Result<SvgOptions, VtracerError> {
+        let mut svg = self.svg.clone();
+        if svg.unit != SvgUnit::Px {
+            svg.dpi = self.dpi(input_path)?;
+        }
+        Ok(svg)
+    }
+
+    fn pdf(&self, input_path: &Path) -> Result<PdfOptions, VtracerError> {
+        let mut pdf = self.pdf.clone();
+        pdf.dpi = self.dpi(input_path)?;
+        Ok(pdf)
+    }
+
+    fn dxf(&self, input_path: &Path) -> Result<DxfOptions, VtracerError> {
+        let mut dxf = self.dxf.clone();
+        if dxf.units != DxfUnits::Unitless {
+            dxf.dpi = self.dpi(input_path)?;
+        }
+        Ok(dxf)
+    }
+
+    fn plotter(&self, input_path: &Path) -> Result<PlotterOptions, VtracerError> {
+        let mm_per_pixel = match self.mm_per_pixel {
+            Some(mm_per_pixel) => mm_per_pixel,
+            None => 25.4 / self.dpi(input_path)?,
+        };
+        Ok(PlotterOptions {
+            mm_per_pixel,
+            ..self.plotter.clone()
+        })
+    }
+}
 
-fn path_simplify_mode_from_str(s: &str) -> PathSimplifyMode {
-    match s {
-        "polygon" => PathSimplifyMode::Polygon,
-        "spline" => PathSimplifyMode::Spline,
-        "none" => PathSimplifyMode::None,
-        _ => panic!("unknown PathSimplifyMode {}", s),
+/// Parse the value of option `name`, recording a malformed value in `invalid`
+fn parse_value<T, F>(
+    matches: &ArgMatches,
+    name: &str,
+    invalid: &mut Vec<InvalidField>,
+    parse: F,
+) -> Option<T>
+where
+    F: FnOnce(&str) -> Result<T, InvalidField>,
+{
+    let value = matches.value_of(name)?.trim();
+    match parse(value) {
+        Ok(value) => Some(value),
+        Err(err) => {
+            invalid.push(err);
+            None
+        }
     }
 }
 
-pub fn config_from_args() -> (PathBuf, PathBuf, Config) {
+/// Parse the value of option `name` as a positive number
+fn parse_positive(
+    matches: &ArgMatches,
+    name: &'static str,
+    invalid: &mut Vec<InvalidField>,
+) -> Option<f64> {
+    parse_value(matches, name, invalid, |value| {
+        value
+            .parse()
+            .ok()
+            .filter(|&number: &f64| number > 0.0)
+            .ok_or_else(|| InvalidField::new(name, value, "a positive number"))
+    })
+}
+
+/// Parse the value of numeric option `name` for the config field `field`
+fn parse_number<T: FromStr>(
+    matches: &ArgMatches,
+    name: &str,
+    field: &'static str,
+    expected: &'static str,
+    invalid: &mut Vec<InvalidField>,
+) -> Option<T> {
+    parse_value(matches, name, invalid, |value| {
+        value
+            .parse()
+            .map_err(|_| InvalidField::new(field, value, expected))
+    })
+}
+
+/// The output files, each with the format to write it in
+pub type Outputs = Vec<(PathBuf, OutputFormat)>;
+
+/// The input path, the outputs, the config, and whether to merge paths of the same color
+pub fn config_from_args() -> Result<(PathBuf, Outputs, Config, bool), VtracerError> {
     let app = App::new("visioncortex VTracer ".to_owned() + env!("CARGO_PKG_VERSION"))
         .about("A cmd app to convert images into vector graphics.");
 
@@ -26,7 +162,7 @@ pub fn config_from_args() -> (PathBuf, PathBuf, Config) {
             .long("input")
             .short("i")
             .takes_value(true)
-            .help("Path to input raster image")
+            .help("Path to input raster image, or to a trace saved as json")
             .required(true),
     );
 
@@ -35,10 +171,28 @@ pub fn config_from_args() -> (PathBuf, PathBuf, Config) {
             .long("output")
             .short("o")
             .takes_value(true)
-            .help("Path to output vector graphics")
+            .multiple(true)
+            .number_of_values(1)
+            .help(
+                "Path to output vector graphics, can be given more than once. \
+            The format follows the extension, svg if it is not known. ",
+            )
             .required(true),
     );
 
+    let app = app.arg(
+        Arg::with_name("format")
+            .long("format")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .help(
+                "Format of the `--output` given before it, instead of the one of its extension: \
+            `svg`, `svgz`, `pdf`, `eps`, `dxf`, `gcode`, `hpgl`, `vectordrawable`, `imagevector`, \
+            `geojson`, `json` or `tikz` ",
+            ),
+    );
+
     let app = app.arg(
         Arg::with_name("color_mode")
             .long("colormode")
@@ -56,6 +210,16 @@ pub fn config_from_args() -> (PathBuf, PathBuf, Config) {
             ),
     );
 
+    let app = app.arg(
+        Arg::with_name("config")
+            .long("config")
+            .takes_value(true)
+            .help(
+                "Path to a TOML or JSON config file. Applied first, \
+            then overridden by `--preset` and the individual options. ",
+            ),
+    );
+
     let app = app.arg(
         Arg::with_name("preset")
             .long("preset")
@@ -124,6 +288,163 @@ pub fn config_from_args() -> (PathBuf, PathBuf, Config) {
             .help("Number of decimal places to use in path string"),
     );
 
+    let app =
+        app.arg(Arg::with_name("merge_paths").long("merge_paths").help(
+            "Merge consecutive paths of the same color where that does not change the image",
+        ));
+
+    let app = app.arg(
+        Arg::with_name("compress")
+            .long("compress")
+            .help("Gzip compress svg output, as is done for the `.svgz` extension"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("group_by_color")
+            .long("group_by_color")
+            .help(
+                "Group consecutive svg paths of the same color into a `<g>`, filled by classes of a `<style>` palette",
+            ),
+    );
+
+    let app = app.arg(
+        Arg::with_name("background")
+            .long("background")
+            .takes_value(true)
+            .help("Color to fill the background of svg output with, as `#RRGGBB`, or `auto` for the color covering the most area"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("coordinates")
+            .long("coordinates")
+            .takes_value(true)
+            .help("Position svg paths by a `transform` of `translated` (default) ones, or write `absolute` or `relative` commands from the image origin without one"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("title")
+            .long("title")
+            .takes_value(true)
+            .help("Title of svg output, announced by screen readers"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("desc")
+            .long("desc")
+            .takes_value(true)
+            .help("Description of svg output"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("id_prefix")
+            .long("id_prefix")
+            .takes_value(true)
+            .help("Give each svg path an `id` of this prefix followed by its index"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("embed_config")
+            .long("embed_config")
+            .help("Embed the config used as json in the `<metadata>` of svg output"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("dpi")
+            .long("dpi")
+            .takes_value(true)
+            .help("Resolution converting pixels into the page size of pdf output, the `--units` of dxf output, the `--svg_unit` of svg output and the millimeters of G-code and HPGL output. Defaults to the resolution of the input image or else 96, at 72 one pixel is one point"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("viewbox")
+            .long("viewbox")
+            .help("Write a `viewBox` so that svg output scales to the size it is displayed at"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("svg_scale")
+            .long("svg_scale")
+            .takes_value(true)
+            .help("Factor to scale the width and height of svg output by, adding a `viewBox`"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("svg_unit")
+            .long("svg_unit")
+            .takes_value(true)
+            .help("Unit of the width and height of svg output `px` (default), `mm`, `in`, `pt`, converted with `--dpi`"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("units")
+            .long("units")
+            .takes_value(true)
+            .help("Drawing units of dxf output `px` (default), `in`, `mm`, `cm`, `m`"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("scale")
+            .long("scale")
+            .takes_value(true)
+            .help("Millimeters per pixel of G-code and HPGL output, following `--dpi` by default"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("tolerance")
+            .long("tolerance")
+            .takes_value(true)
+            .help("Largest deviation (mm) of straight lines from curves in G-code and HPGL output"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("geojson_tolerance")
+            .long("geojson_tolerance")
+            .takes_value(true)
+            .help("Largest deviation (px) of straight lines from curves in GeoJSON output, 0.5 by default"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("feed_rate")
+            .long("feed_rate")
+            .takes_value(true)
+            .help("Drawing speed (mm/min) of G-code and HPGL output"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("travel_rate")
+            .long("travel_rate")
+            .takes_value(true)
+            .help("Speed (mm/min) of pen up moves in G-code output"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("pen_down")
+            .long("pen_down")
+            .takes_value(true)
+            .help("G-code command to lower the pen or turn on the laser, `M3` by default"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("pen_up")
+            .long("pen_up")
+            .takes_value(true)
+            .help("G-code command to raise the pen or turn off the laser, `M5` by default"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("tool_change")
+            .long("tool_change")
+            .takes_value(true)
+            .help("G-code command to switch to the next color, with `{tool}` replaced by its number, like `T{tool} M6`. Empty (default) leaves it out"),
+    );
+
+    let app = app.arg(
+        Arg::with_name("tikz_width")
+            .long("tikz_width")
+            .takes_value(true)
+            .help("TeX length to scale tikz output to, like `\\linewidth`. One pixel is 1pt by default"),
+    );
+
     // Extract matches
     let matches = app.get_matches();
 
@@ -131,152 +452,303 @@ pub fn config_from_args() -> (PathBuf, PathBuf, Config) {
     let input_path = matches
         .value_of("input")
         .expect("Input path is required, please specify it by --input or -i.");
-    let output_path = matches
-        .value_of("output")
-        .expect("Output path is required, please specify it by --output or -o.");
+    let output_paths: Vec<PathBuf> = matches
+        .values_of("output")
+        .expect("Output path is required, please specify it by --output or -o.")
+        .map(PathBuf::from)
+        .collect();
 
     let input_path = PathBuf::from(input_path);
-    let output_path = PathBuf::from(output_path);
 
-    if let Some(value) = matches.value_of("preset") {
-        config = Config::from_preset(Preset::from_str(value).unwrap());
+    if let Some(value) = matches.value_of("config") {
+        config = Config::from_file(Path::new(value))?;
     }
 
-    if let Some(value) = matches.value_of("color_mode") {
-        config.color_mode = ColorMode::from_str(if value.trim() == "bw" || value.trim() == "BW" {
-            "binary"
-        } else {
-            "color"
-        })
-        .unwrap()
+    // Malformed values are collected and reported together with the out of range ones
+    let mut invalid = vec![];
+
+    if let Some(preset) = parse_value(&matches, "preset", &mut invalid, Preset::from_str) {
+        config = Config::from_preset(preset);
     }
 
-    if let Some(value) = matches.value_of("hierarchical") {
-        config.hierarchical = Hierarchical::from_str(value).unwrap()
+    if let Some(value) = parse_value(&matches, "color_mode", &mut invalid, ColorMode::from_str) {
+        config.color_mode = value;
     }
 
-    if let Some(value) = matches.value_of("mode") {
-        let value = value.trim();
-        config.mode = path_simplify_mode_from_str(if value == "pixel" {
-            "none"
-        } else if value == "polygon" {
-            "polygon"
-        } else if value == "spline" {
-            "spline"
-        } else {
-            panic!("Parser Error: Curve fitting mode is invalid: {}", value);
-        });
+    if let Some(value) = parse_value(
+        &matches,
+        "hierarchical",
+        &mut invalid,
+        Hierarchical::from_str,
+    ) {
+        config.hierarchical = value;
     }
 
-    if let Some(value) = matches.value_of("filter_speckle") {
-        if value.trim().parse::<usize>().is_ok() {
-            // is numeric
-            let value = value.trim().parse::<usize>().unwrap();
-            if value > 16 {
-                panic!("Out of Range Error: Filter speckle is invalid at {}. It must be within [0,16].", value);
-            }
-            config.filter_speckle = value;
-        } else {
-            panic!(
-                "Parser Error: Filter speckle is not a positive integer: {}.",
-                value
-            );
-        }
+    if let Some(value) = parse_value(&matches, "mode", &mut invalid, path_simplify_mode_from_str) {
+        config.mode = value;
     }
 
-    if let Some(value) = matches.value_of("color_precision") {
-        if value.trim().parse::<i32>().is_ok() {
-            // is numeric
-            let value = value.trim().parse::<i32>().unwrap();
-            if value < 1 || value > 8 {
-                panic!("Out of Range Error: Color precision is invalid at {}. It must be within [1,8].", value);
-            }
-            config.color_precision = value;
-        } else {
-            panic!(
-                "Parser Error: Color precision is not an integer: {}.",
-                value
-            );
-        }
+    if let Some(value) = parse_number(
+        &matches,
+        "filter_speckle",
+        "filter_speckle",
+        "a positive integer",
+        &mut invalid,
+    ) {
+        config.filter_speckle = value;
     }
 
-    if let Some(value) = matches.value_of("gradient_step") {
-        if value.trim().parse::<i32>().is_ok() {
-            // is numeric
-            let value = value.trim().parse::<i32>().unwrap();
-            if value < 0 || value > 255 {
-                panic!("Out of Range Error: Gradient step is invalid at {}. It must be within [0,255].", value);
-            }
-            config.layer_difference = value;
-        } else {
-            panic!("Parser Error: Gradient step is not an integer: {}.", value);
-        }
+    if let Some(value) = parse_number(
+        &matches,
+        "color_precision",
+        "color_precision",
+        "an integer",
+        &mut invalid,
+    ) {
+        config.color_precision = value;
     }
 
-    if let Some(value) = matches.value_of("corner_threshold") {
-        if value.trim().parse::<i32>().is_ok() {
-            // is numeric
-            let value = value.trim().parse::<i32>().unwrap();
-            if value < 0 || value > 180 {
-                panic!("Out of Range Error: Corner threshold is invalid at {}. It must be within [0,180].", value);
-            }
-            config.corner_threshold = value
-        } else {
-            panic!("Parser Error: Corner threshold is not numeric: {}.", value);
-        }
+    if let Some(value) = parse_number(
+        &matches,
+        "gradient_step",
+        "layer_difference",
+        "an integer",
+        &mut invalid,
+    ) {
+        config.layer_difference = value;
     }
 
-    if let Some(value) = matches.value_of("segment_length") {
-        if value.trim().parse::<f64>().is_ok() {
-            // is numeric
-            let value = value.trim().parse::<f64>().unwrap();
-            if value < 3.5 || value > 10.0 {
-                panic!("Out of Range Error: Segment length is invalid at {}. It must be within [3.5,10].", value);
-            }
-            config.length_threshold = value;
-        } else {
-            panic!("Parser Error: Segment length is not numeric: {}.", value);
-        }
+    if let Some(value) = parse_number(
+        &matches,
+        "corner_threshold",
+        "corner_threshold",
+        "an integer",
+        &mut invalid,
+    ) {
+        config.corner_threshold = value;
+    }
+
+    if let Some(value) = parse_number(
+        &matches,
+        "segment_length",
+        "length_threshold",
+        "a number",
+        &mut invalid,
+    ) {
+        config.length_threshold = value;
     }
 
-    if let Some(value) = matches.value_of("splice_threshold") {
-        if value.trim().parse::<i32>().is_ok() {
-            // is numeric
-            let value = value.trim().parse::<i32>().unwrap();
-            if value < 0 || value > 180 {
-                panic!("Out of Range Error: Segment length is invalid at {}. It must be within [0,180].", value);
+    if let Some(value) = parse_number(
+        &matches,
+        "splice_threshold",
+        "splice_threshold",
+        "an integer",
+        &mut invalid,
+    ) {
+        config.splice_threshold = value;
+    }
+
+    if let Some(value) = parse_number(
+        &matches,
+        "path_precision",
+        "path_precision",
+        "an unsigned integer",
+        &mut invalid,
+    ) {
+        config.path_precision = Some(value);
+    }
+
+    let mut options = OutputOptions {
+        compress: matches.is_present("compress"),
+        ..Default::default()
+    };
+    options.svg.group_by_color = matches.is_present("group_by_color");
+    options.svg.view_box = matches.is_present("viewbox");
+    options.svg.title = matches.value_of("title").map(str::to_owned);
+    options.svg.description = matches.value_of("desc").map(str::to_owned);
+    options.svg.id_prefix = matches.value_of("id_prefix").map(str::to_owned);
+
+    if let Some(value) = parse_value(
+        &matches,
+        "background",
+        &mut invalid,
+        SvgBackground::from_str,
+    ) {
+        options.svg.background = Some(value);
+    }
+
+    if let Some(value) = parse_value(
+        &matches,
+        "coordinates",
+        &mut invalid,
+        PathCoordinates::from_str,
+    ) {
+        options.svg.coordinates = value;
+    }
+
+    if let Some(value) = parse_positive(&matches, "dpi", &mut invalid) {
+        options.dpi = Some(value);
+    }
+
+    if let Some(value) = parse_positive(&matches, "svg_scale", &mut invalid) {
+        options.svg.scale = value;
+    }
+
+    if let Some(value) = parse_value(&matches, "svg_unit", &mut invalid, SvgUnit::from_str) {
+        options.svg.unit = value;
+    }
+
+    if let Some(value) = parse_value(&matches, "units", &mut invalid, DxfUnits::from_str) {
+        options.dxf.units = value;
+    }
+
+    if let Some(value) = parse_positive(&matches, "scale", &mut invalid) {
+        options.mm_per_pixel = Some(value);
+    }
+
+    if let Some(value) = parse_positive(&matches, "tolerance", &mut invalid) {
+        options.plotter.tolerance = value;
+    }
+
+    if let Some(value) = parse_positive(&matches, "geojson_tolerance", &mut invalid) {
+        options.geojson.tolerance = value;
+    }
+
+    if let Some(value) = parse_positive(&matches, "feed_rate", &mut invalid) {
+        options.plotter.feed_rate = value;
+    }
+
+    if let Some(value) = parse_positive(&matches, "travel_rate", &mut invalid) {
+        options.plotter.travel_rate = value;
+    }
+
+    if let Some(value) = matches.value_of("pen_down") {
+        options.plotter.pen_down = value.to_owned();
+    }
+
+    if let Some(value) = matches.value_of("pen_up") {
+        options.plotter.pen_up = value.to_owned();
+    }
+
+    if let Some(value) = matches.value_of("tool_change") {
+        options.plotter.tool_change = value.to_owned();
+    }
+
+    if let Some(value) = matches.value_of("tikz_width") {
+        options.tikz.width = Some(value.to_owned());
+    }
+
+    // A trace read back from json was not made with the config given here
+    if matches.is_present("embed_config") && !has_extension(&input_path, "json") {
+        options.svg.config = Some(config.clone());
+    }
+
+    // Each `--format` applies to the `--output` given right before it
+    let output_indices: Vec<usize> = matches.indices_of("output").into_iter().flatten().collect();
+    let mut formats: Vec<Option<&str>> = vec![None; output_paths.len()];
+    if let (Some(values), Some(indices)) =
+        (matches.values_of("format"), matches.indices_of("format"))
+    {
+        for (format, index) in values.zip(indices) {
+            match output_indices.iter().rposition(|&i| i < index) {
+                Some(output) if formats[output].is_none() => formats[output] = Some(format),
+                _ => invalid.push(InvalidField::new(
+                    "format",
+                    format,
+                    "to follow an `--output` without a format",
+                )),
             }
-            config.splice_threshold = value;
-        } else {
-            panic!("Parser Error: Segment length is not numeric: {}.", value);
         }
     }
+    let mut outputs = vec![];
+    for (i, output_path) in output_paths.into_iter().enumerate() {
+        let format = match formats[i] {
+            Some(format) => match format.trim().parse() {
+                Ok(format) => format,
+                Err(err) => {
+                    invalid.push(err);
+                    continue;
+                }
+            },
+            None => OutputFormat::from_path(&output_path)
+                .unwrap_or(OutputFormat::Svg(SvgOptions::default())),
+        };
+        let format = options.apply(format, &input_path, &output_path)?;
+        outputs.push((output_path, format));
+    }
 
-    if let Some(value) = matches.value_of("path_precision") {
-        if value.trim().parse::<u32>().is_ok() {
-            // is numeric
-            let value = value.trim().parse::<u32>().ok();
-            config.path_precision = value;
-        } else {
-            panic!(
-                "Parser Error: Path precision is not an unsigned integer: {}.",
-                value
-            );
-        }
+    if let Err(VtracerError::InvalidConfig(fields)) = config.validate() {
+        invalid.extend(fields);
+    }
+    if !invalid.is_empty() {
+        return Err(VtracerError::InvalidConfig(invalid));
     }
 
-    (input_path, output_path, config)
+    let merge_paths = matches.is_present("merge_paths");
+
+    Ok((input_path, outputs, config, merge_paths))
+}
+
+/// Convert the input image, or read back a trace saved as `.json`, and write every output
+fn convert(
+    input_path: &Path,
+    outputs: &Outputs,
+    config: Config,
+    merge_paths: bool,
+) -> Result<(), VtracerError> {
+    let mut svg = if has_extension(input_path, "json") {
+        let file = BufReader::new(File::open(input_path)?);
+        SvgFile::read_json(file).map_err(io::Error::from)?
+    } else {
+        vtracer::convert(vtracer::read_image(input_path)?, config)?
+    };
+    if merge_paths {
+        svg.merge_paths();
+    }
+    for (output_path, format) in outputs {
+        write_output_file(&svg, output_path, format)?;
+    }
+    Ok(())
+}
+
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(extension))
+}
+
+/// Name the `ImageVector` of Kotlin output after the file, which Kotlin conventionally matches
+fn kotlin_name(output_path: &Path) -> String {
+    let stem = output_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default();
+    let name: String = stem
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        name
+    } else {
+        format!("Traced{}", name)
+    }
 }
 
 fn main() {
-    let (input_path, output_path, config) = config_from_args();
-    let result = converter::convert_image_to_svg(&input_path, &output_path, config);
+    let (input_path, outputs, config, merge_paths) = match config_from_args() {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+    };
+    let result = convert(&input_path, &outputs, config, merge_paths);
     match result {
         Ok(()) => {
             println!("Conversion successful.");
         }
-        Err(msg) => {
-            panic!("Conversion failed with error message: {}", msg);
+        Err(err) => {
+            eprintln!("Conversion failed with error message: {}", err);
+            process::exit(1);
         }
     }
 }
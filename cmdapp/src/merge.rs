@@ -0,0 +1,153 @@
+use visioncortex::PointF64;
+
+use crate::segments::{subpaths, Segment};
+use crate::svg::{SvgFile, SvgPath};
+
+/// Bounds of a path, as its top left and bottom right corners
+type Bounds = (PointF64, PointF64);
+
+impl SvgFile {
+    /// Merge every run of consecutive paths of the same color into as few paths as possible, so
+    /// that each is written as a single element with one fill. A path only joins the one before
+    /// it when their bounds do not overlap: overlapping paths could cancel each other out under
+    /// the nonzero fill rule, or be composited twice where they are translucent.
+    ///
+    /// A merged path keeps the metadata of its first path, with the `area` and `rect` of all.
+    /// Paths without any points are dropped.
+    pub fn merge_paths(&mut self) {
+        let mut merged: Vec<(SvgPath, Vec<Bounds>)> = vec![];
+        for path in self.paths.drain(..) {
+            let bounds = match bounds(&path) {
+                Some(bounds) => bounds,
+                // Draws nothing
+                None => continue,
+            };
+            if let Some((last, parts)) = merged.last_mut() {
+                if last.color == path.color && !parts.iter().any(|&part| overlap(part, bounds)) {
+                    last.path.paths.extend(path.path.paths);
+                    last.metadata.area += path.metadata.area;
+                    last.metadata.rect.merge(path.metadata.rect);
+                    parts.push(bounds);
+                    continue;
+                }
+            }
+            merged.push((path, vec![bounds]));
+        }
+        self.paths = merged.into_iter().map(|(path, _)| path).collect();
+    }
+}
+
+/// Bounds of every point of `path`, including the control points, which bound its curves
+fn bounds(path: &SvgPath) -> Option<Bounds> {
+    let mut points = subpaths(&path.path)
+        .into_iter()
+        .flatten()
+        .flat_map(|segment| match segment {
+            Segment::Move(a) | Segment::Line(a) => vec![a],
+            Segment::Cubic(a, b, c) => vec![a, b, c],
+        });
+    let first = points.next()?;
+    Some(points.fold((first, first), |(min, max), p| {
+        (
+            PointF64::new(min.x.min(p.x), min.y.min(p.y)),
+            PointF64::new(max.x.max(p.x), max.y.max(p.y)),
+        )
+    }))
+}
+
+/// Whether the interiors of `a` and `b` intersect. Bounds sharing only an edge do not.
+fn overlap(a: Bounds, b: Bounds) -> bool {
+    a.0.x < b.1.x && b.0.x < a.1.x && a.0.y < b.1.y && b.0.y < a.1.y
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use visioncortex::{Color, CompoundPath, CompoundPathElement, PathI32, PointI32};
+
+    const RED: Color = Color {
+        r: 255,
+        g: 0,
+        b: 0,
+        a: 255,
+    };
+    const BLUE: Color = Color {
+        r: 0,
+        g: 0,
+        b: 255,
+        a: 255,
+    };
+
+    fn square(x: i32, y: i32, size: i32) -> CompoundPath {
+        let mut path = PathI32::new();
+        for (dx, dy) in [(0, 0), (size, 0), (size, size), (0, size), (0, 0)] {
+            path.add(PointI32::new(x + dx, y + dy));
+        }
+        let mut compound = CompoundPath::new();
+        compound.paths.push(CompoundPathElement::PathI32(path));
+        compound
+    }
+
+    /// The first point of every subpath of every path, in drawing order
+    fn starts(svg: &SvgFile) -> Vec<Vec<PointF64>> {
+        svg.paths
+            .iter()
+            .map(|path| {
+                subpaths(&path.path)
+                    .into_iter()
+                    .map(|subpath| match subpath[0] {
+                        Segment::Move(a) => a,
+                        segment => panic!("subpath starts with {:?}", segment),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn merges_adjacent_paths_of_a_color_in_drawing_order() {
+        let mut svg = SvgFile::new(100, 100, None);
+        svg.add_path(square(0, 0, 10), RED);
+        svg.add_path(square(20, 0, 10), RED);
+        // Sharing an edge is not overlapping
+        svg.add_path(square(30, 0, 10), RED);
+        svg.merge_paths();
+
+        assert_eq!(svg.paths.len(), 1);
+        let p = |x| PointF64::new(x, 0.0);
+        assert_eq!(starts(&svg), [vec![p(0.0), p(20.0), p(30.0)]]);
+    }
+
+    #[test]
+    fn never_merges_overlapping_paths() {
+        let mut svg = SvgFile::new(100, 100, None);
+        svg.add_path(square(0, 0, 20), RED);
+        svg.add_path(square(50, 50, 10), RED);
+        // Overlaps the first path only
+        svg.add_path(square(10, 10, 20), RED);
+        svg.merge_paths();
+
+        assert_eq!(svg.paths.len(), 2);
+        let p = |x, y| PointF64::new(x, y);
+        assert_eq!(
+            starts(&svg),
+            [vec![p(0.0, 0.0), p(50.0, 50.0)], vec![p(10.0, 10.0)]]
+        );
+    }
+
+    #[test]
+    fn keeps_other_colors_and_paths_in_between_apart() {
+        let mut svg = SvgFile::new(100, 100, None);
+        svg.add_path(square(0, 0, 10), RED);
+        svg.add_path(square(20, 0, 10), BLUE);
+        // Same color as the first, but not adjacent to it
+        svg.add_path(square(40, 0, 10), RED);
+        svg.merge_paths();
+
+        assert_eq!(svg.paths.len(), 3);
+        let colors: Vec<_> = svg.paths.iter().map(|path| path.color.b).collect();
+        assert_eq!(colors, [0, 255, 0]);
+        let p = |x| vec![PointF64::new(x, 0.0)];
+        assert_eq!(starts(&svg), [p(0.0), p(20.0), p(40.0)]);
+    }
+}
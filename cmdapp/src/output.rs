@@ -0,0 +1,112 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::dxf::DxfOptions;
+use crate::error::{InvalidField, VtracerError};
+use crate::geojson::GeoJsonOptions;
+use crate::pdf::PdfOptions;
+use crate::plotter::PlotterOptions;
+use crate::svg::{SvgFile, SvgOptions};
+use crate::tikz::TikzOptions;
+
+/// A format that an `SvgFile` can be written in. Implement it to add a format of your own.
+pub trait OutputWriter {
+    fn write_output(&self, svg: &SvgFile, writer: &mut dyn Write) -> io::Result<()>;
+}
+
+/// The output formats built into vtracer, with their settings
+#[derive(Debug, Clone)]
+pub enum OutputFormat {
+    Svg(SvgOptions),
+    /// Gzip compressed svg
+    Svgz(SvgOptions),
+    Pdf(PdfOptions),
+    Eps,
+    Dxf(DxfOptions),
+    Gcode(PlotterOptions),
+    Hpgl(PlotterOptions),
+    /// Android VectorDrawable xml
+    VectorDrawable,
+    /// Kotlin source declaring a Jetpack Compose `ImageVector` called `name`
+    ImageVector {
+        name: String,
+    },
+    GeoJson(GeoJsonOptions),
+    /// TikZ picture for LaTeX
+    Tikz(TikzOptions),
+    /// The lossless json of `SvgFile::write_json`
+    Json,
+}
+
+impl OutputFormat {
+    /// The format conventionally stored under the extension of `path`, with default settings
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_string_lossy().to_ascii_lowercase();
+        extension.parse().ok()
+    }
+}
+
+/// Parse a format by its name or file extension, with default settings
+impl FromStr for OutputFormat {
+    type Err = InvalidField;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "svg" => Ok(Self::Svg(SvgOptions::default())),
+            "svgz" => Ok(Self::Svgz(SvgOptions::default())),
+            "pdf" => Ok(Self::Pdf(PdfOptions::default())),
+            "eps" | "ps" => Ok(Self::Eps),
+            "dxf" => Ok(Self::Dxf(DxfOptions::default())),
+            "gcode" | "nc" | "ngc" => Ok(Self::Gcode(PlotterOptions::default())),
+            "hpgl" | "plt" => Ok(Self::Hpgl(PlotterOptions::default())),
+            "vectordrawable" | "xml" => Ok(Self::VectorDrawable),
+            "imagevector" | "kt" => Ok(Self::ImageVector {
+                name: "TracedImage".to_owned(),
+            }),
+            "geojson" => Ok(Self::GeoJson(GeoJsonOptions::default())),
+            "json" => Ok(Self::Json),
+            "tikz" | "tex" => Ok(Self::Tikz(TikzOptions::default())),
+            _ => Err(InvalidField::new(
+                "format",
+                s,
+                "`svg`, `svgz`, `pdf`, `eps`, `dxf`, `gcode`, `hpgl`, `vectordrawable`, \
+                `imagevector`, `geojson`, `json` or `tikz`",
+            )),
+        }
+    }
+}
+
+impl OutputWriter for OutputFormat {
+    fn write_output(&self, svg: &SvgFile, writer: &mut dyn Write) -> io::Result<()> {
+        match self {
+            Self::Svg(options) => svg.write_svg(writer, options),
+            Self::Svgz(options) => svg.write_svgz(writer, options),
+            Self::Pdf(options) => svg.write_pdf(writer, options),
+            Self::Eps => svg.write_eps(writer),
+            Self::Dxf(options) => svg.write_dxf(writer, options),
+            Self::Gcode(options) => svg.write_gcode(writer, options),
+            Self::Hpgl(options) => svg.write_hpgl(writer, options),
+            Self::VectorDrawable => svg.write_vector_drawable(writer),
+            Self::ImageVector { name } => svg.write_image_vector(writer, name),
+            Self::GeoJson(options) => svg.write_geojson(writer, options),
+            Self::Json => svg.write_json(writer),
+            Self::Tikz(options) => svg.write_tikz(writer, options),
+        }
+    }
+}
+
+/// Write `svg` into a new file at `output_path` using `output`
+pub fn write_output_file(
+    svg: &SvgFile,
+    output_path: &Path,
+    output: &dyn OutputWriter,
+) -> Result<(), VtracerError> {
+    let mut out_file = BufWriter::new(File::create(output_path)?);
+    output.write_output(svg, &mut out_file)?;
+    // Flush explicitly, as errors are silently dropped when a `BufWriter` goes out of scope
+    out_file.flush()?;
+
+    Ok(())
+}
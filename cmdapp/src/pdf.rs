@@ -0,0 +1,189 @@
+use std::io::{self, Write};
+
+use crate::segments::{format_number, write_postfix_path, PostfixOperators};
+use crate::svg::SvgFile;
+
+const PDF_OPERATORS: PostfixOperators = PostfixOperators {
+    move_to: "m",
+    line_to: "l",
+    curve_to: "c",
+    close_path: "h",
+};
+
+/// Options for `SvgFile::write_pdf`
+#[derive(Debug, Clone)]
+pub struct PdfOptions {
+    /// Resolution to print the image at, 96 by default like the other outputs.
+    /// At 72, one pixel is one point.
+    pub dpi: f64,
+}
+
+impl Default for PdfOptions {
+    fn default() -> Self {
+        Self { dpi: 96.0 }
+    }
+}
+
+impl SvgFile {
+    /// Write a single page PDF, with every path drawn as native PDF path operators.
+    /// The page is sized from `width` and `height` at `options.dpi`.
+    pub fn write_pdf<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        options: &PdfOptions,
+    ) -> io::Result<()> {
+        let mut writer = CountingWriter {
+            inner: writer,
+            count: 0,
+        };
+        let mut offsets = vec![];
+        let scale = 72.0 / options.dpi;
+        let width = format_number(self.width as f64 * scale, Some(3));
+        let height = format_number(self.height as f64 * scale, Some(3));
+
+        // The comment with high bytes marks the file as binary for transfer programs
+        writer.write_all(b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n")?;
+
+        offsets.push(writer.count);
+        write!(
+            writer,
+            "1 0 obj\n<< /Type /Catalog /Pages 2 0 R >>\nendobj\n"
+        )?;
+
+        offsets.push(writer.count);
+        write!(
+            writer,
+            "2 0 obj\n<< /Type /Pages /Kids [3 0 R] /Count 1 >>\nendobj\n"
+        )?;
+
+        offsets.push(writer.count);
+        write!(
+            writer,
+            "3 0 obj\n<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << >> /Contents 4 0 R >>\nendobj\n",
+            width, height
+        )?;
+
+        // The stream is written as it is generated, so its length follows it as object 5
+        offsets.push(writer.count);
+        write!(writer, "4 0 obj\n<< /Length 5 0 R >>\nstream\n")?;
+        let stream_start = writer.count;
+        // Flip the y axis and scale, so that paths are drawn in pixel coordinates
+        writeln!(
+            writer,
+            "{} 0 0 {} 0 {} cm",
+            format_number(scale, Some(6)),
+            format_number(-scale, Some(6)),
+            height
+        )?;
+        for path in &self.paths {
+            if path.path.paths.is_empty() {
+                continue;
+            }
+            writeln!(
+                writer,
+                "{} {} {} rg",
+                format_number(path.color.r as f64 / 255.0, Some(3)),
+                format_number(path.color.g as f64 / 255.0, Some(3)),
+                format_number(path.color.b as f64 / 255.0, Some(3))
+            )?;
+            write_postfix_path(&mut writer, &path.path, self.path_precision, &PDF_OPERATORS)?;
+            // Nonzero winding, the same as the svg default fill rule
+            writeln!(writer, "f")?;
+        }
+        let length = writer.count - stream_start;
+        write!(writer, "\nendstream\nendobj\n")?;
+
+        offsets.push(writer.count);
+        write!(writer, "5 0 obj\n{}\nendobj\n", length)?;
+
+        let xref = writer.count;
+        write!(
+            writer,
+            "xref\n0 {}\n0000000000 65535 f \n",
+            offsets.len() + 1
+        )?;
+        for offset in &offsets {
+            writeln!(writer, "{:010} 00000 n ", offset)?;
+        }
+        write!(
+            writer,
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            offsets.len() + 1,
+            xref
+        )
+    }
+}
+
+/// Keeps track of the byte offsets needed for the cross-reference table
+struct CountingWriter<'a, W: Write + ?Sized> {
+    inner: &'a mut W,
+    count: usize,
+}
+
+impl<W: Write + ?Sized> Write for CountingWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.count += written;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use visioncortex::{Color, CompoundPath, CompoundPathElement, PointF64, Spline};
+
+    fn find(pdf: &[u8], needle: &str, from: usize) -> usize {
+        let needle = needle.as_bytes();
+        from + pdf[from..]
+            .windows(needle.len())
+            .position(|window| window == needle)
+            .unwrap_or_else(|| panic!("no {:?}", String::from_utf8_lossy(needle)))
+    }
+
+    /// The line starting at `from`, without its end of line
+    fn line(pdf: &[u8], from: usize) -> &str {
+        let end = find(pdf, "\n", from);
+        std::str::from_utf8(&pdf[from..end]).unwrap()
+    }
+
+    #[test]
+    fn xref_offsets_and_stream_length_match_the_file() {
+        let mut svg = SvgFile::new(120, 90, Some(2));
+        let mut path = CompoundPath::new();
+        path.paths.push(CompoundPathElement::Spline(Spline {
+            points: [(10.0, 10.0), (40.0, 0.5), (80.0, 20.25), (100.0, 80.0)]
+                .iter()
+                .map(|&(x, y)| PointF64::new(x, y))
+                .collect(),
+        }));
+        svg.add_path(path, Color::new(200, 100, 50));
+        let mut pdf = vec![];
+        svg.write_pdf(&mut pdf, &PdfOptions { dpi: 96.0 }).unwrap();
+
+        let startxref = find(&pdf, "startxref\n", 0) + "startxref\n".len();
+        let xref: usize = line(&pdf, startxref).parse().unwrap();
+        assert_eq!(line(&pdf, xref), "xref");
+        let count: usize = line(&pdf, xref + 5)[2..].parse().unwrap();
+        assert_eq!(count, 6);
+        let entries = find(&pdf, "\n", xref + 5) + 1;
+        assert_eq!(line(&pdf, entries), "0000000000 65535 f ");
+        for object in 1..count {
+            let entry = line(&pdf, entries + 20 * object);
+            let offset: usize = entry[..10].parse().unwrap();
+            assert_eq!(line(&pdf, offset), format!("{} 0 obj", object));
+        }
+
+        // The stream length is the indirect object 5
+        let length_object = find(&pdf, "5 0 obj\n", 0) + "5 0 obj\n".len();
+        let length: usize = line(&pdf, length_object).parse().unwrap();
+        let start = find(&pdf, "stream\n", 0) + "stream\n".len();
+        let end = find(&pdf, "\nendstream", start);
+        assert_eq!(end - start, length);
+        assert!(line(&pdf, start).ends_with(" cm"));
+    }
+}
@@ -0,0 +1,199 @@
+use std::io::{self, Write};
+
+use visioncortex::{Color, PointF64};
+
+use crate::segments::{flatten, format_number, subpaths};
+use crate::svg::SvgFile;
+
+/// Options for `SvgFile::write_gcode` and `SvgFile::write_hpgl`
+#[derive(Debug, Clone)]
+pub struct PlotterOptions {
+    /// Output scale. The default of 25.4 / 96 plots at 96 pixels per inch, like svg.
+    pub mm_per_pixel: f64,
+    /// Largest distance in mm between a curve and the straight lines that replace it
+    pub tolerance: f64,
+    /// Speed in mm/min while drawing
+    pub feed_rate: f64,
+    /// Speed in mm/min of moves between outlines. G-code only.
+    pub travel_rate: f64,
+    /// Command to lower the pen or turn on the laser. G-code only.
+    pub pen_down: String,
+    /// Command to raise the pen or turn off the laser. G-code only.
+    pub pen_up: String,
+    /// Command to switch to the next color, with `{tool}` replaced by its 1-based number, like
+    /// `T{tool} M6`. G-code only, and left out when empty, as it is by default since GRBL
+    /// rejects `M6`. HPGL selects the pen with `SP`.
+    pub tool_change: String,
+}
+
+impl Default for PlotterOptions {
+    fn default() -> Self {
+        Self {
+            mm_per_pixel: 25.4 / 96.0,
+            tolerance: 0.1,
+            feed_rate: 1000.0,
+            travel_rate: 3000.0,
+            pen_down: "M3".to_owned(),
+            pen_up: "M5".to_owned(),
+            tool_change: String::new(),
+        }
+    }
+}
+
+/// Closed polylines in mm of one color, in drawing order
+struct Pen {
+    color: Color,
+    outlines: Vec<Vec<PointF64>>,
+}
+
+impl SvgFile {
+    /// Write G-code that traces the outline of every path, one tool per fill color.
+    /// The origin is the bottom left corner of the image.
+    pub fn write_gcode<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        options: &PlotterOptions,
+    ) -> io::Result<()> {
+        let mm = |v: f64| format_number(v, Some(3));
+        writeln!(
+            writer,
+            "; Generated by visioncortex VTracer {}",
+            env!("CARGO_PKG_VERSION")
+        )?;
+        writeln!(writer, "G21")?;
+        writeln!(writer, "G90")?;
+        writeln!(writer, "{}", options.pen_up)?;
+        for (tool, pen) in self.pens(options).iter().enumerate() {
+            let Color { r, g, b, .. } = pen.color;
+            writeln!(writer, "; Color #{:02x}{:02x}{:02x}", r, g, b)?;
+            if !options.tool_change.is_empty() {
+                let command = options
+                    .tool_change
+                    .replace("{tool}", &(tool + 1).to_string());
+                writeln!(writer, "{}", command)?;
+            }
+            for outline in &pen.outlines {
+                let (start, rest) = match outline.split_first() {
+                    Some(split) => split,
+                    None => continue,
+                };
+                writeln!(
+                    writer,
+                    "G0 X{} Y{} F{}",
+                    mm(start.x),
+                    mm(start.y),
+                    mm(options.travel_rate)
+                )?;
+                writeln!(writer, "{}", options.pen_down)?;
+                let mut feed_rate = Some(options.feed_rate);
+                for p in rest {
+                    write!(writer, "G1 X{} Y{}", mm(p.x), mm(p.y))?;
+                    match feed_rate.take() {
+                        Some(feed_rate) => writeln!(writer, " F{}", mm(feed_rate))?,
+                        None => writeln!(writer)?,
+                    }
+                }
+                writeln!(writer, "{}", options.pen_up)?;
+            }
+        }
+        writeln!(writer, "G0 X0 Y0")?;
+        writeln!(writer, "M2")
+    }
+
+    /// Write HPGL that traces the outline of every path, one pen per fill color.
+    /// The origin is the bottom left corner of the image.
+    pub fn write_hpgl<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        options: &PlotterOptions,
+    ) -> io::Result<()> {
+        // Plotter units are 0.025 mm
+        let unit = |v: f64| (v * 40.0).round() as i64;
+        writeln!(writer, "IN;")?;
+        for (pen_number, pen) in self.pens(options).iter().enumerate() {
+            writeln!(writer, "SP{};", pen_number + 1)?;
+            // Velocity is in cm/s
+            writeln!(
+                writer,
+                "VS{};",
+                format_number(options.feed_rate / 600.0, Some(1))
+            )?;
+            for outline in &pen.outlines {
+                let (start, rest) = match outline.split_first() {
+                    Some(split) => split,
+                    None => continue,
+                };
+                writeln!(writer, "PU{},{};", unit(start.x), unit(start.y))?;
+                let points: Vec<String> = rest
+                    .iter()
+                    .map(|p| format!("{},{}", unit(p.x), unit(p.y)))
+                    .collect();
+                writeln!(writer, "PD{};", points.join(","))?;
+            }
+        }
+        writeln!(writer, "PU;SP0;")
+    }
+
+    /// Flatten the paths into outlines in mm, grouped by color in order of first appearance.
+    /// Within a color, each outline starts at the point closest to where the last one ended.
+    fn pens(&self, options: &PlotterOptions) -> Vec<Pen> {
+        let scale = options.mm_per_pixel;
+        let height = self.height as f64;
+        let tolerance = options.tolerance / scale;
+
+        let mut pens: Vec<Pen> = vec![];
+        for path in &self.paths {
+            let outlines = subpaths(&path.path).into_iter().map(|subpath| {
+                flatten(&subpath, tolerance)
+                    .into_iter()
+                    .map(|p| PointF64::new(p.x * scale, (height - p.y) * scale))
+                    .collect::<Vec<_>>()
+            });
+            match pens.iter_mut().find(|pen| pen.color == path.color) {
+                Some(pen) => pen.outlines.extend(outlines),
+                None => pens.push(Pen {
+                    color: path.color,
+                    outlines: outlines.collect(),
+                }),
+            }
+        }
+
+        let mut position = PointF64::new(0.0, 0.0);
+        for pen in &mut pens {
+            pen.outlines = order_outlines(std::mem::take(&mut pen.outlines), &mut position);
+        }
+        pens
+    }
+}
+
+/// Greedily visit the outline starting nearest to `position`, and start each outline at
+/// its point nearest to where the pen is, to keep pen up moves short
+fn order_outlines(mut outlines: Vec<Vec<PointF64>>, position: &mut PointF64) -> Vec<Vec<PointF64>> {
+    let distance = |a: &PointF64, b: &PointF64| (a.x - b.x).hypot(a.y - b.y);
+    let mut ordered = Vec::with_capacity(outlines.len());
+    while !outlines.is_empty() {
+        let nearest = outlines
+            .iter()
+            .enumerate()
+            .filter_map(|(i, outline)| Some((i, distance(outline.first()?, position))))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map_or(0, |(i, _)| i);
+        let mut outline = outlines.swap_remove(nearest);
+        if outline.len() > 1 {
+            // Drop the closing point, rotate, and close again at the new start
+            outline.pop();
+            let start = outline
+                .iter()
+                .enumerate()
+                .min_by(|a, b| distance(a.1, position).total_cmp(&distance(b.1, position)))
+                .map_or(0, |(i, _)| i);
+            outline.rotate_left(start);
+            outline.push(outline[0]);
+        }
+        if let Some(&last) = outline.last() {
+            *position = last;
+        }
+        ordered.push(outline);
+    }
+    ordered
+}
@@ -1,7 +1,8 @@
 use crate::*;
-use image::{io::Reader, ImageFormat};
-use pyo3::{exceptions::PyException, prelude::*};
-use std::io::{BufReader, Cursor};
+use pyo3::{
+    exceptions::{PyException, PyIOError, PyRuntimeError, PyValueError},
+    prelude::*,
+};
 use std::path::PathBuf;
 use visioncortex::PathSimplifyMode;
 
@@ -37,9 +38,9 @@ fn convert_image_to_svg_py(
         max_iterations,
         splice_threshold,
         path_precision,
-    );
+    )?;
 
-    convert_image_to_svg(&input_path, &output_path, config).unwrap();
+    convert_image_to_svg(&input_path, &output_path, config)?;
     Ok(())
 }
 
@@ -71,31 +72,9 @@ fn convert_raw_image_to_svg(
         max_iterations,
         splice_threshold,
         path_precision,
-    );
-    let mut img_reader = Reader::new(BufReader::new(Cursor::new(img_bytes)));
-    let img_format = img_format.and_then(|ext_name| ImageFormat::from_extension(ext_name));
-    let img = match img_format {
-        Some(img_format) => {
-            img_reader.set_format(img_format);
-            img_reader.decode()
-        }
-        None => img_reader
-            .with_guessed_format()
-            .map_err(|_| PyException::new_err("Unrecognized image format. "))?
-            .decode(),
-    };
-    let img = match img {
-        Ok(img) => img.to_rgba8(),
-        Err(_) => return Err(PyException::new_err("Failed to decode img_bytes. ")),
-    };
-    let (width, height) = (img.width() as usize, img.height() as usize);
-    let img = ColorImage {
-        pixels: img.as_raw().to_vec(),
-        width,
-        height,
-    };
-    let svg =
-        convert(img, config).map_err(|_| PyException::new_err("Failed to convert the image. "))?;
+    )?;
+    let img_format = img_format.and_then(ImageFormat::from_extension);
+    let svg = convert_bytes(&img_bytes, img_format, config)?;
     Ok(format!("{}", svg))
 }
 
@@ -137,7 +116,7 @@ fn convert_pixels_to_svg(
         max_iterations,
         splice_threshold,
         path_precision,
-    );
+    )?;
     let mut flat_pixels: Vec<u8> = vec![];
     for (r, g, b, a) in rgba_pixels {
         flat_pixels.push(r);
@@ -149,11 +128,25 @@ fn convert_pixels_to_svg(
     img.pixels = flat_pixels;
     (img.width, img.height) = size;
 
-    let svg =
-        convert(img, config).map_err(|_| PyException::new_err("Failed to convert the image. "))?;
+    let svg = convert(img, config)?;
     Ok(format!("{}", svg))
 }
 
+impl From<VtracerError> for PyErr {
+    fn from(err: VtracerError) -> Self {
+        match err {
+            VtracerError::Decode(_)
+            | VtracerError::ConfigParse(_)
+            | VtracerError::InvalidConfig(_)
+            | VtracerError::WorldFile(..) => PyValueError::new_err(err.to_string()),
+            VtracerError::Io(_) => PyIOError::new_err(err.to_string()),
+            VtracerError::Keying | VtracerError::Cancelled => {
+                PyRuntimeError::new_err(err.to_string())
+            }
+        }
+    }
+}
+
 fn construct_config(
     colormode: Option<&str>,
     hierarchical: Option<&str>,
@@ -166,26 +159,31 @@ fn construct_config(
     max_iterations: Option<usize>,
     splice_threshold: Option<i32>,
     path_precision: Option<u32>,
-) -> Config {
-    // TODO: enforce color mode with an enum so that we only
-    // accept the strings 'color' or 'binary'
-    let color_mode = match colormode.unwrap_or("color") {
-        "color" => ColorMode::Color,
-        "binary" => ColorMode::Binary,
-        _ => ColorMode::Color,
+) -> Result<Config, VtracerError> {
+    let mut invalid = vec![];
+
+    let color_mode = match colormode.unwrap_or("color").parse() {
+        Ok(color_mode) => color_mode,
+        Err(err) => {
+            invalid.push(err);
+            ColorMode::Color
+        }
     };
 
-    let hierarchical = match hierarchical.unwrap_or("stacked") {
-        "stacked" => Hierarchical::Stacked,
-        "cutout" => Hierarchical::Cutout,
-        _ => Hierarchical::Stacked,
+    let hierarchical = match hierarchical.unwrap_or("stacked").parse() {
+        Ok(hierarchical) => hierarchical,
+        Err(err) => {
+            invalid.push(err);
+            Hierarchical::Stacked
+        }
     };
 
-    let mode = match mode.unwrap_or("spline") {
-        "spline" => PathSimplifyMode::Spline,
-        "polygon" => PathSimplifyMode::Polygon,
-        "none" => PathSimplifyMode::None,
-        _ => PathSimplifyMode::Spline,
+    let mode = match path_simplify_mode_from_str(mode.unwrap_or("spline")) {
+        Ok(mode) => mode,
+        Err(err) => {
+            invalid.push(err);
+            PathSimplifyMode::Spline
+        }
     };
 
     let filter_speckle = filter_speckle.unwrap_or(4);
@@ -196,7 +194,7 @@ fn construct_config(
     let splice_threshold = splice_threshold.unwrap_or(45);
     let max_iterations = max_iterations.unwrap_or(10);
 
-    Config {
+    let config = Config {
         color_mode,
         hierarchical,
         filter_speckle,
@@ -209,6 +207,13 @@ fn construct_config(
         splice_threshold,
         path_precision,
         ..Default::default()
+    };
+
+    invalid.extend(config.invalid_fields());
+    if invalid.is_empty() {
+        Ok(config)
+    } else {
+        Err(VtracerError::InvalidConfig(invalid))
     }
 }
 
@@ -0,0 +1,243 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Resolution metadata is near the start of the file
+const HEADER_LENGTH: u64 = 64 * 1024;
+
+/// Read the resolution stored in an image file, in dots per inch. It is found in the `pHYs` chunk
+/// of a png, or the JFIF or else Exif header of a jpeg. Other formats, or files without it, give
+/// `None`.
+pub fn read_image_dpi(input_path: &Path) -> io::Result<Option<f64>> {
+    let mut header = vec![];
+    File::open(input_path)?
+        .take(HEADER_LENGTH)
+        .read_to_end(&mut header)?;
+    Ok(png_dpi(&header).or_else(|| jpeg_dpi(&header)))
+}
+
+fn png_dpi(bytes: &[u8]) -> Option<f64> {
+    let mut chunks = bytes.strip_prefix(b"\x89PNG\r\n\x1a\n")?;
+    while chunks.len() >= 8 {
+        let length = u32::from_be_bytes(chunks[0..4].try_into().ok()?) as usize;
+        let kind = &chunks[4..8];
+        let data = chunks.get(8..8 + length)?;
+        match kind {
+            b"pHYs" if length == 9 => {
+                let x = u32::from_be_bytes(data[0..4].try_into().ok()?);
+                // Unit 1 is the meter, otherwise only the aspect ratio is known
+                return (data[8] == 1 && x > 0).then_some(x as f64 * 0.0254);
+            }
+            // The chunk comes before the image data
+            b"IDAT" | b"IEND" => return None,
+            _ => {}
+        }
+        // Skip the data and its crc
+        chunks = chunks.get(8 + length + 4..)?;
+    }
+    None
+}
+
+fn jpeg_dpi(bytes: &[u8]) -> Option<f64> {
+    let mut segments = bytes.strip_prefix(b"\xff\xd8")?;
+    let (mut jfif, mut exif) = (None, None);
+    while let [0xff, marker, rest @ ..] = segments {
+        match *marker {
+            // Padding before a marker
+            0xff => {
+                segments = &segments[1..];
+                continue;
+            }
+            // Markers without a segment
+            0x01 | 0xd0..=0xd7 => {
+                segments = rest;
+                continue;
+            }
+            // The image data starts, or ends
+            0xda | 0xd9 => break,
+            _ => {}
+        }
+        let length = u16::from_be_bytes([*rest.first()?, *rest.get(1)?]) as usize;
+        // A segment cut off by the end of the header is read as far as it goes
+        let data = rest.get(2..length.max(2).min(rest.len()))?;
+        match *marker {
+            0xe0 if jfif.is_none() => jfif = data.strip_prefix(b"JFIF\0").and_then(jfif_dpi),
+            0xe1 if exif.is_none() => exif = data.strip_prefix(b"Exif\0\0").and_then(exif_dpi),
+            _ => {}
+        }
+        match rest.get(length..) {
+            Some(next) => segments = next,
+            None => break,
+        }
+    }
+    jfif.or(exif)
+}
+
+/// The density of a JFIF header, after the `JFIF\0` identifier
+fn jfif_dpi(jfif: &[u8]) -> Option<f64> {
+    let x = u16::from_be_bytes([*jfif.get(3)?, *jfif.get(4)?]) as f64;
+    match jfif[2] {
+        _ if x == 0.0 => None,
+        1 => Some(x),
+        2 => Some(x * 2.54),
+        // Unit 0 only gives the aspect ratio
+        _ => None,
+    }
+}
+
+/// The resolution in the first IFD of Exif data, after the `Exif\0\0` identifier
+fn exif_dpi(tiff: &[u8]) -> Option<f64> {
+    let big_endian = match tiff.get(0..4)? {
+        b"MM\0*" => true,
+        b"II*\0" => false,
+        _ => return None,
+    };
+    let u16_at = |offset: usize| {
+        let bytes = [*tiff.get(offset)?, *tiff.get(offset + 1)?];
+        Some(if big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    };
+    let u32_at = |offset: usize| {
+        let bytes: [u8; 4] = tiff.get(offset..offset + 4)?.try_into().ok()?;
+        Some(if big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    };
+    let rational_at = |offset: usize| {
+        let (numerator, denominator) = (u32_at(offset)?, u32_at(offset + 4)?);
+        (numerator > 0 && denominator > 0).then(|| numerator as f64 / denominator as f64)
+    };
+
+    let ifd = u32_at(4)? as usize;
+    let (mut x, mut y, mut unit) = (None, None, 2);
+    for i in 0..u16_at(ifd)? as usize {
+        let entry = ifd + 2 + 12 * i;
+        match u16_at(entry)? {
+            // Rationals are stored at an offset
+            0x011a => x = rational_at(u32_at(entry + 8)? as usize),
+            0x011b => y = rational_at(u32_at(entry + 8)? as usize),
+            0x0128 => unit = u16_at(entry + 8)?,
+            _ => {}
+        }
+    }
+    let resolution = x.or(y)?;
+    match unit {
+        2 => Some(resolution),
+        3 => Some(resolution * 2.54),
+        // Unit 1 only gives the aspect ratio
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(marker: u8, data: &[u8]) -> Vec<u8> {
+        let mut segment = vec![0xff, marker];
+        segment.extend_from_slice(&(data.len() as u16 + 2).to_be_bytes());
+        segment.extend_from_slice(data);
+        segment
+    }
+
+    fn jpeg(segments: &[Vec<u8>]) -> Vec<u8> {
+        let mut jpeg = vec![0xff, 0xd8];
+        for segment in segments {
+            jpeg.extend_from_slice(segment);
+        }
+        // Start of scan, followed by data that looks like markers
+        jpeg.extend(segment(0xda, &[0; 10]));
+        jpeg.extend([0xff, 0xe0, 0, 4, 1, 2, 0xff, 0xd9]);
+        jpeg
+    }
+
+    fn jfif(unit: u8, density: u16) -> Vec<u8> {
+        let mut data = b"JFIF\0\x01\x02".to_vec();
+        data.push(unit);
+        data.extend(density.to_be_bytes());
+        data.extend(density.to_be_bytes());
+        data.extend([0, 0]);
+        segment(0xe0, &data)
+    }
+
+    /// An Exif segment holding only XResolution and ResolutionUnit, as a camera writes it
+    fn exif(big_endian: bool, resolution: u32, unit: u16) -> Vec<u8> {
+        let u16_bytes = |v: u16| {
+            if big_endian {
+                v.to_be_bytes()
+            } else {
+                v.to_le_bytes()
+            }
+        };
+        let u32_bytes = |v: u32| {
+            if big_endian {
+                v.to_be_bytes()
+            } else {
+                v.to_le_bytes()
+            }
+        };
+        let mut tiff = if big_endian {
+            b"MM\0*".to_vec()
+        } else {
+            b"II*\0".to_vec()
+        };
+        tiff.extend(u32_bytes(8));
+        // The IFD: a make string to skip, XResolution pointing after the IFD, ResolutionUnit
+        tiff.extend(u16_bytes(3));
+        tiff.extend(u16_bytes(0x010f));
+        tiff.extend(u16_bytes(2));
+        tiff.extend(u32_bytes(4));
+        tiff.extend(b"ACME");
+        tiff.extend(u16_bytes(0x011a));
+        tiff.extend(u16_bytes(5));
+        tiff.extend(u32_bytes(1));
+        tiff.extend(u32_bytes(8 + 2 + 3 * 12 + 4));
+        tiff.extend(u16_bytes(0x0128));
+        tiff.extend(u16_bytes(3));
+        tiff.extend(u32_bytes(1));
+        tiff.extend(u16_bytes(unit));
+        tiff.extend([0, 0]);
+        // No next IFD
+        tiff.extend(u32_bytes(0));
+        tiff.extend(u32_bytes(resolution * 10));
+        tiff.extend(u32_bytes(10));
+
+        let mut data = b"Exif\0\0".to_vec();
+        data.extend(tiff);
+        segment(0xe1, &data)
+    }
+
+    #[test]
+    fn reads_the_density_of_jfif() {
+        assert_eq!(jpeg_dpi(&jpeg(&[jfif(1, 300)])), Some(300.0));
+        assert_eq!(jpeg_dpi(&jpeg(&[jfif(2, 100)])), Some(254.0));
+        assert_eq!(jpeg_dpi(&jpeg(&[jfif(0, 1)])), None);
+    }
+
+    #[test]
+    fn reads_the_resolution_of_exif_in_either_byte_order() {
+        let quantization = segment(0xdb, &[0; 65]);
+        let camera = jpeg(&[exif(true, 350, 2), quantization.clone()]);
+        assert_eq!(jpeg_dpi(&camera), Some(350.0));
+        let camera = jpeg(&[exif(false, 100, 3), quantization]);
+        assert_eq!(jpeg_dpi(&camera), Some(254.0));
+    }
+
+    #[test]
+    fn prefers_jfif_and_falls_back_to_exif() {
+        assert_eq!(
+            jpeg_dpi(&jpeg(&[jfif(1, 72), exif(true, 300, 2)])),
+            Some(72.0)
+        );
+        assert_eq!(
+            jpeg_dpi(&jpeg(&[jfif(0, 1), exif(true, 300, 2)])),
+            Some(300.0)
+        );
+        assert_eq!(jpeg_dpi(&jpeg(&[segment(0xdb, &[0; 65])])), None);
+    }
+}
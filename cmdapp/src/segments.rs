@@ -0,0 +1,293 @@
+use std::io::{self, Write};
+use std::mem;
+use visioncortex::{CompoundPath, CompoundPathElement, PointF64};
+
+/// A drawing command in absolute pixel coordinates
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Segment {
+    Move(PointF64),
+    Line(PointF64),
+    /// Cubic bezier through two control points to an end point
+    Cubic(PointF64, PointF64, PointF64),
+}
+
+/// Split `path` into its subpaths. Every subpath starts with a `Move` and is implicitly closed.
+pub(crate) fn subpaths(path: &CompoundPath) -> Vec<Vec<Segment>> {
+    path.paths
+        .iter()
+        .filter_map(|element| match element {
+            CompoundPathElement::PathI32(path) => polygon(
+                path.path
+                    .iter()
+                    .map(|p| PointF64::new(p.x as f64, p.y as f64)),
+            ),
+            CompoundPathElement::PathF64(path) => polygon(path.path.iter().copied()),
+            CompoundPathElement::Spline(spline) => {
+                let (&start, rest) = spline.points.split_first()?;
+                let mut segments = vec![Segment::Move(start)];
+                segments.extend(
+                    rest.chunks_exact(3)
+                        .map(|c| Segment::Cubic(c[0], c[1], c[2])),
+                );
+                Some(segments)
+            }
+        })
+        .collect()
+}
+
+fn polygon(points: impl Iterator<Item = PointF64>) -> Option<Vec<Segment>> {
+    let mut points: Vec<PointF64> = points.collect();
+    // The closing point is implied
+    if points.len() > 1 && points.first() == points.last() {
+        points.pop();
+    }
+    let (&start, rest) = points.split_first()?;
+    let mut segments = vec![Segment::Move(start)];
+    segments.extend(rest.iter().map(|&p| Segment::Line(p)));
+    Some(segments)
+}
+
+/// Format a coordinate with at most `precision` decimal places, without trailing zeros
+pub(crate) fn format_number(value: f64, precision: Option<u32>) -> String {
+    let string = match precision {
+        Some(precision) => format!("{:.*}", precision as usize, value),
+        None => format!("{}", value),
+    };
+    let string = if string.contains('.') {
+        string.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        &string
+    };
+    match string {
+        "-0" => "0".to_owned(),
+        string => string.to_owned(),
+    }
+}
+
+/// Format `path` as svg path data in absolute coordinates, so that it needs no transform
+pub(crate) fn path_data(path: &CompoundPath, precision: Option<u32>) -> String {
+    let p = |point: PointF64| {
+        format!(
+            "{},{}",
+            format_number(point.x, precision),
+            format_number(point.y, precision)
+        )
+    };
+    let mut commands = vec![];
+    for subpath in subpaths(path) {
+        for segment in subpath {
+            commands.push(match segment {
+                Segment::Move(a) => format!("M{}", p(a)),
+                Segment::Line(a) => format!("L{}", p(a)),
+                Segment::Cubic(a, b, c) => format!("C{} {} {}", p(a), p(b), p(c)),
+            });
+        }
+        commands.push("Z".to_owned());
+    }
+    commands.join(" ")
+}
+
+/// Svg path data of `path` in relative commands, each from the end of the previous one
+pub(crate) fn relative_path_data(path: &CompoundPath, precision: Option<u32>) -> String {
+    // Offsets are taken between points rounded as absolute coordinates would be written, so
+    // that rounding errors do not accumulate
+    let round = |v: f64| format_number(v, precision).parse().unwrap_or(v);
+    let round = |p: PointF64| PointF64::new(round(p.x), round(p.y));
+    let d = |from: PointF64, to: PointF64| {
+        format!(
+            "{},{}",
+            format_number(to.x - from.x, precision),
+            format_number(to.y - from.y, precision)
+        )
+    };
+    let mut current = PointF64::default();
+    let mut commands = vec![];
+    for subpath in subpaths(path) {
+        let mut start = current;
+        for segment in subpath {
+            commands.push(match segment {
+                Segment::Move(a) => {
+                    let a = round(a);
+                    start = a;
+                    format!("m{}", d(mem::replace(&mut current, a), a))
+                }
+                Segment::Line(a) => {
+                    let a = round(a);
+                    format!("l{}", d(mem::replace(&mut current, a), a))
+                }
+                Segment::Cubic(a, b, c) => {
+                    // The control points are also relative to the start of the curve
+                    let (a, b, c) = (round(a), round(b), round(c));
+                    let from = mem::replace(&mut current, c);
+                    format!("c{} {} {}", d(from, a), d(from, b), d(from, c))
+                }
+            });
+        }
+        commands.push("z".to_owned());
+        // Closing returns to the start of the subpath
+        current = start;
+    }
+    commands.join(" ")
+}
+
+/// Operator names of a postfix path syntax, like that of PDF or PostScript
+pub(crate) struct PostfixOperators {
+    pub move_to: &'static str,
+    pub line_to: &'static str,
+    pub curve_to: &'static str,
+    pub close_path: &'static str,
+}
+
+/// Write every subpath of `path` in postfix syntax, one operator per line
+pub(crate) fn write_postfix_path<W: Write + ?Sized>(
+    writer: &mut W,
+    path: &CompoundPath,
+    precision: Option<u32>,
+    operators: &PostfixOperators,
+) -> io::Result<()> {
+    let p = |v: f64| format_number(v, precision);
+    for subpath in subpaths(path) {
+        for segment in subpath {
+            match segment {
+                Segment::Move(a) => {
+                    writeln!(writer, "{} {} {}", p(a.x), p(a.y), operators.move_to)?
+                }
+                Segment::Line(a) => {
+                    writeln!(writer, "{} {} {}", p(a.x), p(a.y), operators.line_to)?
+                }
+                Segment::Cubic(a, b, c) => writeln!(
+                    writer,
+                    "{} {} {} {} {} {} {}",
+                    p(a.x),
+                    p(a.y),
+                    p(b.x),
+                    p(b.y),
+                    p(c.x),
+                    p(c.y),
+                    operators.curve_to
+                )?,
+            }
+        }
+        writeln!(writer, "{}", operators.close_path)?;
+    }
+    Ok(())
+}
+
+/// Approximate a subpath by a polyline that stays within `tolerance` of its curves.
+/// The polyline is closed, ending at its first point.
+pub(crate) fn flatten(subpath: &[Segment], tolerance: f64) -> Vec<PointF64> {
+    let mut points: Vec<PointF64> = vec![];
+    for segment in subpath {
+        match *segment {
+            Segment::Move(p) | Segment::Line(p) => points.push(p),
+            Segment::Cubic(c1, c2, p1) => {
+                let p0 = *points.last().unwrap_or(&c1);
+                // Uniform steps deviate at most by 1/8 of the largest second derivative
+                // over the square of their count
+                let deviation = |a: PointF64, b: PointF64, c: PointF64| {
+                    (a.x - 2.0 * b.x + c.x).hypot(a.y - 2.0 * b.y + c.y)
+                };
+                let d = deviation(p0, c1, c2).max(deviation(c1, c2, p1));
+                let steps = ((0.75 * d / tolerance).sqrt().ceil() as usize).max(1);
+                for step in 1..=steps {
+                    let t = step as f64 / steps as f64;
+                    let u = 1.0 - t;
+                    let (a, b, c, e) = (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+                    points.push(PointF64::new(
+                        a * p0.x + b * c1.x + c * c2.x + e * p1.x,
+                        a * p0.y + b * c1.y + c * c2.y + e * p1.y,
+                    ));
+                }
+            }
+        }
+    }
+    if let (Some(&first), Some(&last)) = (points.first(), points.last()) {
+        if first != last {
+            points.push(first);
+        }
+    }
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use visioncortex::{PathF64, PathI32, PointI32, Spline};
+
+    /// The absolute end and control points of svg path data, in order
+    fn points(d: &str) -> Vec<PointF64> {
+        let mut points = vec![];
+        let (mut current, mut start) = (PointF64::default(), PointF64::default());
+        let mut relative = false;
+        let mut origin = current;
+        for token in d.split_whitespace() {
+            let coordinates = token.trim_start_matches(char::is_alphabetic);
+            if let Some(command) = token.chars().next().filter(|c| c.is_alphabetic()) {
+                relative = command.is_lowercase();
+                origin = current;
+                if command.eq_ignore_ascii_case(&'z') {
+                    current = start;
+                    continue;
+                }
+            }
+            let (x, y) = coordinates.split_once(',').unwrap();
+            let mut point = PointF64::new(x.parse().unwrap(), y.parse().unwrap());
+            if relative {
+                // Every point of a command is relative to where the command starts
+                point = PointF64::new(origin.x + point.x, origin.y + point.y);
+            }
+            if token.starts_with(['M', 'm']) {
+                start = point;
+            }
+            current = point;
+            points.push(point);
+        }
+        points
+    }
+
+    #[test]
+    fn relative_path_data_draws_the_same_points_as_path_data() {
+        let mut polygon = PathI32::new();
+        for (x, y) in [(3, 4), (90, 4), (90, 70), (3, 70), (3, 4)] {
+            polygon.add(PointI32::new(x, y));
+        }
+        let mut hole = PathF64::new();
+        for (x, y) in [(10.333, 10.666), (20.125, 10.666), (20.125, 30.999)] {
+            hole.add(PointF64::new(x, y));
+        }
+        let spline = Spline {
+            points: [
+                (50.005, 50.004),
+                (60.1, 40.9),
+                (70.55, 45.45),
+                (80.0, 50.0),
+                (70.0, 60.0),
+                (55.5, 60.0),
+                (50.005, 50.004),
+            ]
+            .iter()
+            .map(|&(x, y)| PointF64::new(x, y))
+            .collect(),
+        };
+        let mut path = CompoundPath::new();
+        path.paths.push(CompoundPathElement::PathI32(polygon));
+        path.paths.push(CompoundPathElement::PathF64(hole));
+        path.paths.push(CompoundPathElement::Spline(spline));
+
+        for precision in [None, Some(0), Some(2)] {
+            let absolute = points(&path_data(&path, precision));
+            let relative = points(&relative_path_data(&path, precision));
+            assert_eq!(absolute.len(), 14);
+            assert_eq!(absolute.len(), relative.len());
+            for (a, r) in absolute.iter().zip(&relative) {
+                assert!(
+                    (a.x - r.x).abs() < 1e-9 && (a.y - r.y).abs() < 1e-9,
+                    "{:?} != {:?} at precision {:?}",
+                    a,
+                    r,
+                    precision
+                );
+            }
+        }
+    }
+}
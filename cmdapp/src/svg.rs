@@ -1,5 +1,14 @@
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
 use std::fmt;
-use visioncortex::{Color, CompoundPath, PointF64};
+use std::io::{self, Write};
+use std::str::FromStr;
+use visioncortex::{BoundingRect, Color, CompoundPath, PointF64};
+
+use crate::config::Config;
+use crate::error::InvalidField;
+use crate::segments::{format_number, path_data, relative_path_data};
 
 #[derive(Debug, Clone)]
 pub struct SvgFile {
@@ -13,6 +22,190 @@ pub struct SvgFile {
 pub struct SvgPath {
     pub path: CompoundPath,
     pub color: Color,
+    pub metadata: PathMetadata,
+}
+
+/// What the clustering knew about the region a path was traced from
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PathMetadata {
+    /// Number of pixels in the cluster
+    pub area: usize,
+    /// Bounding rect of the cluster, in pixels
+    #[serde(with = "BoundingRectDef")]
+    pub rect: BoundingRect,
+    /// Index of the cluster in the `Clusters` it came from
+    pub cluster_index: u32,
+    /// Stacking depth of the cluster, always 0 in binary mode
+    pub depth: u32,
+}
+
+/// Options for `SvgFile::write_svg`
+#[derive(Debug, Clone)]
+pub struct SvgOptions {
+    /// Put the paths into a `<g>` per color, in order of first appearance, with the fill set by
+    /// a class from a `<style>` palette. Only consecutive paths share a `<g>`, so a color has a
+    /// group for every run of its paths, and the paths keep their drawing order.
+    pub group_by_color: bool,
+    /// Write a `viewBox` of the image in pixels, so the svg scales to whatever size it is given.
+    /// It is always written when the size is scaled or not in pixels.
+    pub view_box: bool,
+    /// Factor to multiply the width and height by
+    pub scale: f64,
+    /// Unit of the width and height
+    pub unit: SvgUnit,
+    /// Pixels per inch of the image, to size it in physical units
+    pub dpi: f64,
+    /// How the position of each path is written
+    pub coordinates: PathCoordinates,
+    /// Fill the image with a `<rect>` under the paths
+    pub background: Option<SvgBackground>,
+    /// Text of a `<title>`, which screen readers announce as the name of the image
+    pub title: Option<String>,
+    /// Text of a `<desc>`, a longer description of the image
+    pub description: Option<String>,
+    /// Give each path an `id` of this prefix followed by its index in `SvgFile::paths`
+    pub id_prefix: Option<String>,
+    /// The config the image was traced with, embedded as json in the `<metadata>`. It can be
+    /// passed back to `Config::from_file` from a `.json` file to trace it again the same way.
+    pub config: Option<Config>,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        Self {
+            group_by_color: false,
+            view_box: false,
+            scale: 1.0,
+            unit: SvgUnit::Px,
+            dpi: 96.0,
+            coordinates: PathCoordinates::Translated,
+            background: None,
+            title: None,
+            description: None,
+            id_prefix: None,
+            config: None,
+        }
+    }
+}
+
+impl SvgOptions {
+    /// Length in `unit` of `pixels` pixels of the image
+    fn length(&self, pixels: usize) -> f64 {
+        let pixels = pixels as f64 * self.scale;
+        match self.unit {
+            SvgUnit::Px => pixels,
+            SvgUnit::Mm => pixels / self.dpi * 25.4,
+            SvgUnit::In => pixels / self.dpi,
+            SvgUnit::Pt => pixels / self.dpi * 72.0,
+        }
+    }
+}
+
+/// How the paths of an svg are positioned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathCoordinates {
+    /// Relative to the start of the path, which is moved into place by a `transform`
+    Translated,
+    /// Absolute commands in image coordinates, without a `transform`
+    Absolute,
+    /// Relative commands starting from the image origin, without a `transform`
+    Relative,
+}
+
+impl FromStr for PathCoordinates {
+    type Err = InvalidField;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "translated" => Ok(Self::Translated),
+            "absolute" => Ok(Self::Absolute),
+            "relative" => Ok(Self::Relative),
+            _ => Err(InvalidField::new(
+                "coordinates",
+                s,
+                "`translated`, `absolute` or `relative`",
+            )),
+        }
+    }
+}
+
+/// Color of the background `<rect>` of an svg
+#[derive(Debug, Clone, Copy)]
+pub enum SvgBackground {
+    /// The color covering the largest area of the image, according to the `PathMetadata`
+    Auto,
+    Color(Color),
+}
+
+/// Parse `auto`, or a color as `#RRGGBB` or `#RRGGBBAA`
+impl FromStr for SvgBackground {
+    type Err = InvalidField;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidField::new("background", s, "`auto`, `#RRGGBB` or `#RRGGBBAA`");
+        if s == "auto" {
+            return Ok(Self::Auto);
+        }
+        let hex = s.strip_prefix('#').unwrap_or(s);
+        if !matches!(hex.len(), 6 | 8) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+        let channel = |i: usize| {
+            hex.get(i..i + 2)
+                .map_or(Ok(255), |channel| u8::from_str_radix(channel, 16))
+                .map_err(|_| invalid())
+        };
+        Ok(Self::Color(Color::new_rgba(
+            channel(0)?,
+            channel(2)?,
+            channel(4)?,
+            channel(6)?,
+        )))
+    }
+}
+
+/// Unit of the width and height of an svg
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SvgUnit {
+    Px,
+    Mm,
+    In,
+    Pt,
+}
+
+impl SvgUnit {
+    fn suffix(self) -> &'static str {
+        match self {
+            Self::Px => "",
+            Self::Mm => "mm",
+            Self::In => "in",
+            Self::Pt => "pt",
+        }
+    }
+}
+
+impl FromStr for SvgUnit {
+    type Err = InvalidField;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "px" => Ok(Self::Px),
+            "mm" => Ok(Self::Mm),
+            "in" => Ok(Self::In),
+            "pt" => Ok(Self::Pt),
+            _ => Err(InvalidField::new("unit", s, "`px`, `mm`, `in` or `pt`")),
+        }
+    }
+}
+
+/// Mirror of `BoundingRect` for serde
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "BoundingRect")]
+struct BoundingRectDef {
+    left: i32,
+    top: i32,
+    right: i32,
+    bottom: i32,
 }
 
 impl SvgFile {
@@ -26,11 +219,199 @@ impl SvgFile {
     }
 
     pub fn add_path(&mut self, path: CompoundPath, color: Color) {
-        self.paths.push(SvgPath { path, color })
+        self.add_path_with_metadata(path, color, PathMetadata::default())
+    }
+
+    pub fn add_path_with_metadata(
+        &mut self,
+        path: CompoundPath,
+        color: Color,
+        metadata: PathMetadata,
+    ) {
+        self.paths.push(SvgPath {
+            path,
+            color,
+            metadata,
+        })
+    }
+
+    /// Stream the svg into `writer` one path at a time, without first building the whole document
+    pub fn write_to<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        self.write_svg(writer, &SvgOptions::default())
+    }
+
+    /// Stream the svg written with `options` into `writer`
+    pub fn write_svg<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        options: &SvgOptions,
+    ) -> io::Result<()> {
+        write!(writer, "{}", SvgDocument { svg: self, options })
+    }
+
+    /// Stream the svg into `writer` gzip compressed, as an `.svgz` file
+    pub fn write_svgz<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        options: &SvgOptions,
+    ) -> io::Result<()> {
+        let mut encoder = GzEncoder::new(writer, Compression::default());
+        self.write_svg(&mut encoder, options)?;
+        encoder.finish()?;
+        Ok(())
+    }
+
+    /// The color whose paths cover the most pixels, or that of the first path when the paths
+    /// have no metadata
+    fn background_color(&self) -> Option<Color> {
+        let groups = self.color_groups();
+        let area = |paths: &[usize]| {
+            paths
+                .iter()
+                .map(|&i| self.paths[i].metadata.area)
+                .sum::<usize>()
+        };
+        // The first of equally large colors
+        let (color, _) = groups.iter().rev().max_by_key(|(_, paths)| area(paths))?;
+        Some(*color)
+    }
+
+    /// The distinct colors in order of first appearance, each with the indices of its paths in
+    /// drawing order
+    fn color_groups(&self) -> Vec<(Color, Vec<usize>)> {
+        let mut groups: Vec<(Color, Vec<usize>)> = vec![];
+        for (i, path) in self.paths.iter().enumerate() {
+            match groups.iter_mut().find(|(color, _)| *color == path.color) {
+                Some((_, paths)) => paths.push(i),
+                None => groups.push((path.color, vec![i])),
+            }
+        }
+        groups
     }
 }
 
 impl fmt::Display for SvgFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        SvgDocument {
+            svg: self,
+            options: &SvgOptions::default(),
+        }
+        .fmt(f)
+    }
+}
+
+/// `svg` as written with `options`
+struct SvgDocument<'a> {
+    svg: &'a SvgFile,
+    options: &'a SvgOptions,
+}
+
+impl fmt::Display for SvgDocument<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let precision = self.svg.path_precision;
+        let coordinates = self.options.coordinates;
+        let id = |index: usize| {
+            let prefix = self.options.id_prefix.as_ref()?;
+            Some(format!("{}{}", prefix, index))
+        };
+        write!(f, "{}", SvgHeader(self.svg, self.options))?;
+        if let Some(title) = &self.options.title {
+            writeln!(f, "<title>{}</title>", escape(title))?;
+        }
+        if let Some(description) = &self.options.description {
+            writeln!(f, "<desc>{}</desc>", escape(description))?;
+        }
+        if let Some(config) = &self.options.config {
+            let json = serde_json::to_string(config).map_err(|_| fmt::Error)?;
+            writeln!(f, "<metadata>")?;
+            writeln!(
+                f,
+                r#"<vtracer:config xmlns:vtracer="https://github.com/visioncortex/vtracer">{}</vtracer:config>"#,
+                escape(&json)
+            )?;
+            writeln!(f, "</metadata>")?;
+        }
+        let background = match self.options.background {
+            Some(SvgBackground::Auto) => self.svg.background_color(),
+            Some(SvgBackground::Color(color)) => Some(color),
+            None => None,
+        };
+        if let Some(color) = background {
+            write!(
+                f,
+                r#"<rect width="{}" height="{}" fill="{}""#,
+                self.svg.width,
+                self.svg.height,
+                color.to_hex_string()
+            )?;
+            if let Some(opacity) = opacity(&color) {
+                write!(f, r#" fill-opacity="{}""#, opacity)?;
+            }
+            writeln!(f, "/>")?;
+        }
+        if self.options.group_by_color {
+            let groups = self.svg.color_groups();
+            writeln!(f, "<style>")?;
+            for (i, (color, _)) in groups.iter().enumerate() {
+                write!(f, ".color{} {{ fill: {};", i, color.to_hex_string())?;
+                if let Some(opacity) = opacity(color) {
+                    write!(f, " fill-opacity: {};", opacity)?;
+                }
+                writeln!(f, " }}")?;
+            }
+            writeln!(f, "</style>")?;
+            let mut classes = vec![0; self.svg.paths.len()];
+            for (class, (_, paths)) in groups.iter().enumerate() {
+                for &index in paths {
+                    classes[index] = class;
+                }
+            }
+            // Regrouping the paths by color would change which one is painted on top
+            let mut group = None;
+            for (index, path) in self.svg.paths.iter().enumerate() {
+                let class = classes[index];
+                if group != Some(class) {
+                    if group.is_some() {
+                        writeln!(f, "</g>")?;
+                    }
+                    writeln!(f, r#"<g class="color{}">"#, class)?;
+                    group = Some(class);
+                }
+                let fill = false;
+                PathElement {
+                    path,
+                    id: id(index),
+                    precision,
+                    fill,
+                    coordinates,
+                }
+                .fmt(f)?;
+            }
+            if group.is_some() {
+                writeln!(f, "</g>")?;
+            }
+        } else {
+            for (index, path) in self.svg.paths.iter().enumerate() {
+                let fill = true;
+                PathElement {
+                    path,
+                    id: id(index),
+                    precision,
+                    fill,
+                    coordinates,
+                }
+                .fmt(f)?;
+            }
+        }
+
+        writeln!(f, "</svg>")
+    }
+}
+
+/// Everything before the first path: the xml declaration and the opening `<svg>` tag
+struct SvgHeader<'a>(&'a SvgFile, &'a SvgOptions);
+
+impl fmt::Display for SvgHeader<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         writeln!(f, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
         writeln!(
@@ -38,38 +419,149 @@ impl fmt::Display for SvgFile {
             r#"<!-- Generator: visioncortex VTracer {} -->"#,
             env!("CARGO_PKG_VERSION")
         )?;
-        writeln!(
+        let SvgHeader(svg, options) = self;
+        let length = |pixels| {
+            let length = format_number(options.length(pixels), Some(3));
+            format!("{}{}", length, options.unit.suffix())
+        };
+        write!(
             f,
-            r#"<svg version="1.1" xmlns="http://www.w3.org/2000/svg" width="{}" height="{}">"#,
-            self.width, self.height
+            r#"<svg version="1.1" xmlns="http://www.w3.org/2000/svg" width="{}" height="{}""#,
+            length(svg.width),
+            length(svg.height)
         )?;
-
-        for path in &self.paths {
-            path.fmt_with_precision(f, self.path_precision)?;
+        // Without it the paths would not follow the size
+        if options.view_box || options.unit != SvgUnit::Px || options.scale != 1.0 {
+            write!(f, r#" viewBox="0 0 {} {}""#, svg.width, svg.height)?;
         }
+        writeln!(f, ">")
+    }
+}
 
-        writeln!(f, "</svg>")
+/// A `<path>` element. It has no fill of its own when the fill is set by its group.
+struct PathElement<'a> {
+    path: &'a SvgPath,
+    id: Option<String>,
+    precision: Option<u32>,
+    fill: bool,
+    coordinates: PathCoordinates,
+}
+
+impl fmt::Display for PathElement<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let path = &self.path.path;
+        let (string, offset) = match self.coordinates {
+            PathCoordinates::Translated => {
+                let (string, offset) =
+                    path.to_svg_string(true, PointF64::default(), self.precision);
+                (string, Some(offset))
+            }
+            PathCoordinates::Absolute => (path_data(path, self.precision), None),
+            PathCoordinates::Relative => (relative_path_data(path, self.precision), None),
+        };
+        write!(f, "<path")?;
+        if let Some(id) = &self.id {
+            write!(f, " id=\"{}\"", escape_attribute(id))?;
+        }
+        write!(f, " d=\"{}\"", string)?;
+        if self.fill {
+            let color = &self.path.color;
+            write!(f, " fill=\"{}\"", color.to_hex_string())?;
+            if let Some(opacity) = opacity(color) {
+                write!(f, " fill-opacity=\"{}\"", opacity)?;
+            }
+        }
+        if let Some(offset) = offset {
+            write!(f, " transform=\"translate({},{})\"", offset.x, offset.y)?;
+        }
+        writeln!(f, "/>")
     }
 }
 
+/// The alpha of `color` as an opacity, when it is not opaque
+fn opacity(color: &Color) -> Option<String> {
+    (color.a < 255).then(|| format_number(color.a as f64 / 255.0, Some(3)))
+}
+
+/// Escape `text` for use as xml content
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Escape `text` for use in a double quoted xml attribute
+fn escape_attribute(text: &str) -> String {
+    escape(text).replace('"', "&quot;")
+}
+
 impl fmt::Display for SvgPath {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        self.fmt_with_precision(f, None)
+        PathElement {
+            path: self,
+            id: None,
+            precision: None,
+            fill: true,
+            coordinates: PathCoordinates::Translated,
+        }
+        .fmt(f)
     }
 }
 
-impl SvgPath {
-    fn fmt_with_precision(&self, f: &mut fmt::Formatter, precision: Option<u32>) -> fmt::Result {
-        let (string, offset) = self
-            .path
-            .to_svg_string(true, PointF64::default(), precision);
-        writeln!(
-            f,
-            "<path d=\"{}\" fill=\"{}\" transform=\"translate({},{})\"/>",
-            string,
-            self.color.to_hex_string(),
-            offset.x,
-            offset.y
-        )
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use visioncortex::{CompoundPathElement, PathI32, PointI32};
+
+    fn square(x: i32, y: i32, size: i32) -> CompoundPath {
+        let mut path = PathI32::new();
+        for (dx, dy) in [(0, 0), (size, 0), (size, size), (0, size), (0, 0)] {
+            path.add(PointI32::new(x + dx, y + dy));
+        }
+        let mut compound = CompoundPath::new();
+        compound.paths.push(CompoundPathElement::PathI32(path));
+        compound
+    }
+
+    fn path_data_in_order(svg: &SvgFile, options: &SvgOptions) -> Vec<String> {
+        let mut out = vec![];
+        svg.write_svg(&mut out, options).unwrap();
+        String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .filter(|line| line.starts_with("<path"))
+            .map(|line| line.split('"').nth(1).unwrap().to_owned())
+            .collect()
+    }
+
+    #[test]
+    fn grouping_by_color_keeps_the_drawing_order() {
+        let red = Color::new(255, 0, 0);
+        let gray = Color::new(0xF0, 0xF0, 0xF0);
+        let mut svg = SvgFile::new(40, 40, None);
+        svg.add_path(square(0, 0, 30), red);
+        svg.add_path(square(10, 10, 10), gray);
+        svg.add_path(square(20, 20, 20), red);
+
+        let flat = path_data_in_order(&svg, &SvgOptions::default());
+        let options = SvgOptions {
+            group_by_color: true,
+            ..Default::default()
+        };
+        assert_eq!(flat.len(), 3);
+        assert_eq!(path_data_in_order(&svg, &options), flat);
+
+        let mut out = vec![];
+        svg.write_svg(&mut out, &options).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let groups: Vec<&str> = out.lines().filter(|l| l.starts_with("<g ")).collect();
+        assert_eq!(
+            groups,
+            [
+                r#"<g class="color0">"#,
+                r#"<g class="color1">"#,
+                r#"<g class="color0">"#
+            ]
+        );
     }
 }
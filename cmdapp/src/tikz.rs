@@ -0,0 +1,110 @@
+use std::io::{self, Write};
+
+use visioncortex::Color;
+
+use crate::segments::{format_number, subpaths, Segment};
+use crate::svg::SvgFile;
+
+/// Options for `SvgFile::write_tikz`
+#[derive(Debug, Clone)]
+pub struct TikzOptions {
+    /// Size of a pixel in pt
+    pub scale: f64,
+    /// A TeX length, like `\linewidth`, to scale the picture to instead. Needs the graphicx package.
+    pub width: Option<String>,
+}
+
+impl Default for TikzOptions {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            width: None,
+        }
+    }
+}
+
+impl SvgFile {
+    /// Write a TikZ picture for LaTeX, with a `\fill` per path. The colors are defined by
+    /// `\definecolor` before the picture, once each.
+    pub fn write_tikz<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        options: &TikzOptions,
+    ) -> io::Result<()> {
+        writeln!(
+            writer,
+            "% Generator: visioncortex VTracer {}",
+            env!("CARGO_PKG_VERSION")
+        )?;
+        let mut colors = vec![];
+        for path in &self.paths {
+            let name = color_name(&path.color);
+            if !colors.contains(&name) {
+                let Color { r, g, b, .. } = path.color;
+                writeln!(
+                    writer,
+                    "\\definecolor{{{}}}{{RGB}}{{{},{},{}}}",
+                    name, r, g, b
+                )?;
+                colors.push(name);
+            }
+        }
+
+        // Pixel coordinates are used as is, with the y axis pointing down
+        let unit = match &options.width {
+            Some(width) => {
+                // Needs graphicx. Scaling by a factor instead is too imprecise in TeX.
+                write!(writer, "\\resizebox{{{}}}{{!}}{{", width)?;
+                "1pt".to_owned()
+            }
+            None => format!("{}pt", format_number(options.scale, Some(6))),
+        };
+        writeln!(writer, "\\begin{{tikzpicture}}[x={}, y=-{}]", unit, unit)?;
+        // Keep the margins of the image, rather than fitting the picture to the paths
+        writeln!(
+            writer,
+            "\\useasboundingbox (0,0) rectangle ({},{});",
+            self.width, self.height
+        )?;
+        let p = |x: f64, y: f64| {
+            format!(
+                "({},{})",
+                format_number(x, self.path_precision),
+                format_number(y, self.path_precision)
+            )
+        };
+        for path in &self.paths {
+            let subpaths = subpaths(&path.path);
+            if subpaths.is_empty() {
+                continue;
+            }
+            write!(writer, "\\fill[color={}]", color_name(&path.color))?;
+            for subpath in subpaths {
+                for segment in subpath {
+                    match segment {
+                        Segment::Move(a) => write!(writer, " {}", p(a.x, a.y))?,
+                        Segment::Line(a) => write!(writer, " -- {}", p(a.x, a.y))?,
+                        Segment::Cubic(a, b, c) => write!(
+                            writer,
+                            " .. controls {} and {} .. {}",
+                            p(a.x, a.y),
+                            p(b.x, b.y),
+                            p(c.x, c.y)
+                        )?,
+                    }
+                }
+                write!(writer, " -- cycle")?;
+            }
+            writeln!(writer, ";")?;
+        }
+        write!(writer, "\\end{{tikzpicture}}")?;
+        if options.width.is_some() {
+            write!(writer, "}}")?;
+        }
+        writeln!(writer)
+    }
+}
+
+fn color_name(color: &Color) -> String {
+    format!("vtracer{:02X}{:02X}{:02X}", color.r, color.g, color.b)
+}
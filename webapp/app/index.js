@@ -415,14 +415,14 @@ function restart() {
         'canvas_id': canvas.id,
         'svg_id': svg.id,
         'mode': mode,
-        'clustering_mode': clustering_mode,
+        'color_mode': clustering_mode,
         'hierarchical': clustering_hierarchical,
-        'corner_threshold': deg2rad(globalcorner),
+        'corner_threshold': globalcorner,
         'length_threshold': globallength,
         'max_iterations': 10,
-        'splice_threshold': deg2rad(globalsplice),
-        'filter_speckle': globalfilterspeckle*globalfilterspeckle,
-        'color_precision': 8-globalcolorprecision,
+        'splice_threshold': globalsplice,
+        'filter_speckle': globalfilterspeckle,
+        'color_precision': globalcolorprecision,
         'layer_difference': globallayerdifference,
         'path_precision': globalpathprecision,
     });
@@ -435,10 +435,6 @@ function restart() {
     runner.run();
 }
 
-function deg2rad(deg) {
-    return deg/180*3.141592654;
-}
-
 class ConverterRunner {
     constructor (converter_params) {
         this.converter =
@@ -1,21 +1,17 @@
 use wasm_bindgen::prelude::*;
-use visioncortex::{clusters::Clusters, Color, ColorImage, ColorName, PathSimplifyMode};
+use visioncortex::{clusters::Clusters, Color, ColorImage, ColorName};
+use vtracer::{Config, ConverterConfig, VtracerError};
 
 use crate::{canvas::*, svg::*};
 use serde::Deserialize;
-use super::util;
 
+/// The converter settings use the same schema as the cmd app's `Config`
 #[derive(Debug, Deserialize, Clone)]
 pub struct BinaryImageConverterParams {
     pub canvas_id: String,
     pub svg_id: String,
-    pub mode: String,
-    pub corner_threshold: f64,
-    pub length_threshold: f64,
-    pub max_iterations: usize,
-    pub splice_threshold: f64,
-    pub filter_speckle: usize,
-    pub path_precision: u32,
+    #[serde(flatten)]
+    pub config: Config,
 }
 
 #[wasm_bindgen]
@@ -24,27 +20,27 @@ pub struct BinaryImageConverter {
     svg: Option<Svg>,
     clusters: Clusters,
     counter: usize,
-    mode: PathSimplifyMode,
-    params: BinaryImageConverterParams,
+    config: ConverterConfig,
 }
 
 impl BinaryImageConverter {
     /// Sử dụng DOM canvas như trước
-    pub fn new(params: BinaryImageConverterParams) -> Self {
+    pub fn new(params: BinaryImageConverterParams) -> Result<Self, VtracerError> {
+        params.config.validate()?;
         let canvas = Canvas::new_from_id(&params.canvas_id);
         let svg = Svg::new_from_id(&params.svg_id);
-        Self {
+        Ok(Self {
             canvas: Some(canvas),
             svg: Some(svg),
             clusters: Clusters::default(),
             counter: 0,
-            mode: util::path_simplify_mode(&params.mode),
-            params,
-        }
+            config: params.config.into_converter_config(),
+        })
     }
 
     /// Khởi tạo từ byte buffer RGBA
-    pub fn from_bytes(bytes: &[u8], width: usize, height: usize, params: BinaryImageConverterParams) -> Self {
+    pub fn from_bytes(bytes: &[u8], width: usize, height: usize, params: BinaryImageConverterParams) -> Result<Self, VtracerError> {
+        params.config.validate()?;
         let image = ColorImage {
             pixels: bytes.to_vec(),
             width,
@@ -52,22 +48,22 @@ impl BinaryImageConverter {
         };
         let binary_image = image.to_binary_image(|x| x.r < 128);
         let clusters = binary_image.to_clusters(false);
-        Self {
+        Ok(Self {
             canvas: None,
             svg: None,
             clusters,
             counter: 0,
-            mode: util::path_simplify_mode(&params.mode),
-            params,
-        }
+            config: params.config.into_converter_config(),
+        })
     }
 }
 
 #[wasm_bindgen]
 impl BinaryImageConverter {
-    pub fn new_with_string(params: String) -> Self {
-        let params: BinaryImageConverterParams = serde_json::from_str(params.as_str()).unwrap();
-        Self::new(params)
+    pub fn new_with_string(params: String) -> Result<BinaryImageConverter, JsValue> {
+        let params: BinaryImageConverterParams = serde_json::from_str(params.as_str())
+            .map_err(|err| JsValue::from_str(&err.to_string()))?;
+        Self::new(params).map_err(|err| JsValue::from_str(&err.to_string()))
     }
 
     pub fn init(&mut self) {
@@ -91,17 +87,17 @@ impl BinaryImageConverter {
                 canvas.log(&format!("tick {}", self.counter));
             }
             let cluster = self.clusters.get_cluster(self.counter);
-            if cluster.size() >= self.params.filter_speckle {
+            if cluster.size() >= self.config.filter_speckle_area {
                 let paths = cluster.to_compound_path(
-                    self.mode,
-                    self.params.corner_threshold,
-                    self.params.length_threshold,
-                    self.params.max_iterations,
-                    self.params.splice_threshold,
+                    self.config.mode,
+                    self.config.corner_threshold,
+                    self.config.length_threshold,
+                    self.config.max_iterations,
+                    self.config.splice_threshold,
                 );
                 let color = Color::color(&ColorName::Black);
                 if let Some(svg) = &mut self.svg {
-                    svg.prepend_path(&paths, &color, Some(self.params.path_precision));
+                    svg.prepend_path(&paths, &color, self.config.path_precision);
                 }
             }
             self.counter += 1;
@@ -115,7 +111,7 @@ impl BinaryImageConverter {
     }
 
     pub fn progress(&self) -> u32 {
-        if self.clusters.len() == 0 {
+        if self.clusters.is_empty() {
             100
         } else {
             100 * self.counter as u32 / self.clusters.len() as u32
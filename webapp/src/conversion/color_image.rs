@@ -1,27 +1,18 @@
 use wasm_bindgen::prelude::*;
-use visioncortex::{Color, ColorImage, PathSimplifyMode};
+use visioncortex::{Color, ColorImage};
 use visioncortex::color_clusters::{Clusters, Runner, RunnerConfig, HIERARCHICAL_MAX, IncrementalBuilder, KeyingAction};
+use vtracer::{Config, ConverterConfig, Hierarchical, VtracerError};
 
 use crate::{canvas::*, svg::*};
 use serde::Deserialize;
-use super::util;
-
-const KEYING_THRESHOLD: f32 = 0.2;
 
+/// The converter settings use the same schema as the cmd app's `Config`
 #[derive(Debug, Deserialize, Clone)]
 pub struct ColorImageConverterParams {
     pub canvas_id: String,
     pub svg_id: String,
-    pub mode: String,
-    pub hierarchical: String,
-    pub corner_threshold: f64,
-    pub length_threshold: f64,
-    pub max_iterations: usize,
-    pub splice_threshold: f64,
-    pub filter_speckle: usize,
-    pub color_precision: i32,
-    pub layer_difference: i32,
-    pub path_precision: u32,
+    #[serde(flatten)]
+    pub config: Config,
 }
 
 #[wasm_bindgen]
@@ -30,8 +21,7 @@ pub struct ColorImageConverter {
     svg: Option<Svg>,
     stage: Stage,
     counter: usize,
-    mode: PathSimplifyMode,
-    params: ColorImageConverterParams,
+    config: ConverterConfig,
 }
 
 pub enum Stage {
@@ -42,114 +32,77 @@ pub enum Stage {
 }
 
 impl ColorImageConverter {
-    pub fn new(params: ColorImageConverterParams) -> Self {
+    pub fn new(params: ColorImageConverterParams) -> Result<Self, VtracerError> {
+        params.config.validate()?;
         let canvas = Canvas::new_from_id(&params.canvas_id);
         let svg = Svg::new_from_id(&params.svg_id);
-        Self {
+        Ok(Self {
             canvas: Some(canvas),
             svg: Some(svg),
             stage: Stage::New,
             counter: 0,
-            mode: util::path_simplify_mode(&params.mode),
-            params,
-        }
+            config: params.config.into_converter_config(),
+        })
     }
 
-    pub fn from_bytes(image_data: &[u8], width: usize, height: usize, params: ColorImageConverterParams) -> Self {
+    pub fn from_bytes(image_data: &[u8], width: usize, height: usize, params: ColorImageConverterParams) -> Result<Self, VtracerError> {
+        params.config.validate()?;
+        let config = params.config.into_converter_config();
         let mut image = ColorImage {
             pixels: image_data.to_vec(),
             width,
             height,
         };
 
-        let key_color = if Self::should_key_image(&image) {
-            if let Ok(key) = Self::find_unused_color_in_image(&image) {
-                for y in 0..height {
-                    for x in 0..width {
-                        if image.get_pixel(x, y).a == 0 {
-                            image.set_pixel(x, y, &key);
-                        }
-                    }
-                }
-                key
-            } else {
-                Color::default()
-            }
-        } else {
-            Color::default()
-        };
+        let key_color = vtracer::key_image(&mut image)?;
 
         let runner = Runner::new(RunnerConfig {
-            diagonal: params.layer_difference == 0,
+            diagonal: config.layer_difference == 0,
             hierarchical: HIERARCHICAL_MAX,
             batch_size: 25600,
-            good_min_area: params.filter_speckle,
+            good_min_area: config.filter_speckle_area,
             good_max_area: width * height,
-            is_same_color_a: params.color_precision,
+            is_same_color_a: config.color_precision_loss,
             is_same_color_b: 1,
-            deepen_diff: params.layer_difference,
+            deepen_diff: config.layer_difference,
             hollow_neighbours: 1,
             key_color,
-            keying_action: if params.hierarchical == "cutout" {
+            keying_action: if matches!(config.hierarchical, Hierarchical::Cutout) {
                 KeyingAction::Keep
             } else {
                 KeyingAction::Discard
             },
         }, image);
 
-        Self {
+        Ok(Self {
             canvas: None,
             svg: Some(Svg::new_from_id(&params.svg_id)),
             stage: Stage::Clustering(runner.start()),
             counter: 0,
-            mode: util::path_simplify_mode(&params.mode),
-            params,
-        }
-    }
-}
-
-#[wasm_bindgen]
-impl ColorImageConverter {
-    pub fn new_with_string(params: String) -> Self {
-        let params: ColorImageConverterParams = serde_json::from_str(&params).unwrap();
-        Self::new(params)
+            config,
+        })
     }
 
-    pub fn init(&mut self) {
+    pub fn init_state(&mut self) -> Result<(), VtracerError> {
         if let Some(canvas) = &self.canvas {
             let width = canvas.width() as u32;
             let height = canvas.height() as u32;
             let mut image = canvas.get_image_data_as_color_image(0, 0, width, height);
 
-            let key_color = if Self::should_key_image(&image) {
-                if let Ok(key) = Self::find_unused_color_in_image(&image) {
-                    for y in 0..height as usize {
-                        for x in 0..width as usize {
-                            if image.get_pixel(x, y).a == 0 {
-                                image.set_pixel(x, y, &key);
-                            }
-                        }
-                    }
-                    key
-                } else {
-                    Color::default()
-                }
-            } else {
-                Color::default()
-            };
+            let key_color = vtracer::key_image(&mut image)?;
 
             let runner = Runner::new(RunnerConfig {
-                diagonal: self.params.layer_difference == 0,
+                diagonal: self.config.layer_difference == 0,
                 hierarchical: HIERARCHICAL_MAX,
                 batch_size: 25600,
-                good_min_area: self.params.filter_speckle,
+                good_min_area: self.config.filter_speckle_area,
                 good_max_area: (width * height) as usize,
-                is_same_color_a: self.params.color_precision,
+                is_same_color_a: self.config.color_precision_loss,
                 is_same_color_b: 1,
-                deepen_diff: self.params.layer_difference,
+                deepen_diff: self.config.layer_difference,
                 hollow_neighbours: 1,
                 key_color,
-                keying_action: if self.params.hierarchical == "cutout" {
+                keying_action: if matches!(self.config.hierarchical, Hierarchical::Cutout) {
                     KeyingAction::Keep
                 } else {
                     KeyingAction::Discard
@@ -158,6 +111,20 @@ impl ColorImageConverter {
 
             self.stage = Stage::Clustering(runner.start());
         }
+        Ok(())
+    }
+}
+
+#[wasm_bindgen]
+impl ColorImageConverter {
+    pub fn new_with_string(params: String) -> Result<ColorImageConverter, JsValue> {
+        let params: ColorImageConverterParams = serde_json::from_str(&params)
+            .map_err(|err| JsValue::from_str(&err.to_string()))?;
+        Self::new(params).map_err(|err| JsValue::from_str(&err.to_string()))
+    }
+
+    pub fn init(&mut self) -> Result<(), JsValue> {
+        self.init_state().map_err(|err| JsValue::from_str(&err.to_string()))
     }
 
     pub fn tick(&mut self) -> bool {
@@ -169,7 +136,7 @@ impl ColorImageConverter {
                 }
                 if builder.tick() {
                     let result = builder.result();
-                    if self.params.hierarchical == "cutout" {
+                    if matches!(self.config.hierarchical, Hierarchical::Cutout) {
                         let view = result.view();
                         let image = view.to_color_image();
                         let runner = Runner::new(RunnerConfig {
@@ -211,14 +178,14 @@ impl ColorImageConverter {
                     let paths = cluster.to_compound_path(
                         &view,
                         false,
-                        self.mode,
-                        self.params.corner_threshold,
-                        self.params.length_threshold,
-                        self.params.max_iterations,
-                        self.params.splice_threshold,
+                        self.config.mode,
+                        self.config.corner_threshold,
+                        self.config.length_threshold,
+                        self.config.max_iterations,
+                        self.config.splice_threshold,
                     );
                     if let Some(svg) = &mut self.svg {
-                        svg.prepend_path(&paths, &cluster.residue_color(), Some(self.params.path_precision));
+                        svg.prepend_path(&paths, &cluster.residue_color(), self.config.path_precision);
                     }
                     self.counter += 1;
                     false
@@ -243,53 +210,4 @@ impl ColorImageConverter {
         };
         progress as i32
     }
-
-    fn color_exists_in_image(img: &ColorImage, color: Color) -> bool {
-        for y in 0..img.height {
-            for x in 0..img.width {
-                let p = img.get_pixel(x, y);
-                if p.r == color.r && p.g == color.g && p.b == color.b {
-                    return true;
-                }
-            }
-        }
-        false
-    }
-
-    fn find_unused_color_in_image(img: &ColorImage) -> Result<Color, String> {
-        let candidates = [
-            Color::new(255, 0, 0),
-            Color::new(0, 255, 0),
-            Color::new(0, 0, 255),
-            Color::new(255, 255, 0),
-            Color::new(0, 255, 255),
-            Color::new(255, 0, 255),
-            Color::new(128, 128, 128),
-        ];
-        for color in candidates {
-            if !Self::color_exists_in_image(img, color) {
-                return Ok(color);
-            }
-        }
-        Err("unable to find unused color in image to use as key".into())
-    }
-
-    fn should_key_image(img: &ColorImage) -> bool {
-        if img.width == 0 || img.height == 0 {
-            return false;
-        }
-        let threshold = ((img.width * 2) as f32 * KEYING_THRESHOLD) as usize;
-        let mut count = 0;
-        for &y in &[0, img.height / 4, img.height / 2, 3 * img.height / 4, img.height - 1] {
-            for x in 0..img.width {
-                if img.get_pixel(x, y).a == 0 {
-                    count += 1;
-                    if count >= threshold {
-                        return true;
-                    }
-                }
-            }
-        }
-        false
-    }
 }
@@ -1,3 +1,2 @@
 pub mod binary_image;
 pub mod color_image;
-mod util;
@@ -1,21 +1,67 @@
-use std::ffi::CStr;
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
 use std::os::raw::{c_char, c_int, c_uchar};
+use std::ptr;
 use std::slice;
 
+use serde::de::DeserializeOwned;
+
 use crate::conversion::binary_image::{BinaryImageConverter, BinaryImageConverterParams};
 use crate::conversion::color_image::{ColorImageConverter, ColorImageConverterParams};
 
+// ===========================
+// === Errors ===
+// ===========================
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// The error of the last constructor or `init_state` call on this thread, or null if it succeeded.
+/// Invalid params list every invalid field. The string is owned by the library and stays valid
+/// until the next of those calls on the same thread.
+#[no_mangle]
+pub extern "C" fn vtracer_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |err| err.as_ptr()))
+}
+
+fn set_last_error(error: Option<String>) {
+    // An interior nul would truncate the message anyway
+    let error = error.map(|err| CString::new(err.replace('\0', "")).unwrap_or_default());
+    LAST_ERROR.with(|last| *last.borrow_mut() = error);
+}
+
+fn parse_params<P: DeserializeOwned>(json_params: *const c_char) -> Result<P, String> {
+    let c_str = unsafe { CStr::from_ptr(json_params) };
+    let json = c_str.to_str().map_err(|err| err.to_string())?;
+    serde_json::from_str(json).map_err(|err| err.to_string())
+}
+
+fn into_raw<T>(converter: Result<T, String>) -> *mut T {
+    match converter {
+        Ok(converter) => {
+            set_last_error(None);
+            Box::into_raw(Box::new(converter))
+        }
+        Err(err) => {
+            set_last_error(Some(err));
+            ptr::null_mut()
+        }
+    }
+}
+
 // ===========================
 // === BinaryImageConverter ===
 // ===========================
 
+// The constructors return null when the params are not valid JSON or fail `Config::validate`,
+// with the reason in `vtracer_last_error`.
+
 #[no_mangle]
 pub extern "C" fn vtracer_binary_init(json_params: *const c_char) -> *mut BinaryImageConverter {
-    let c_str = unsafe { CStr::from_ptr(json_params) };
-    let json = c_str.to_str().unwrap();
-    let params: BinaryImageConverterParams = serde_json::from_str(json).unwrap();
-    let converter = BinaryImageConverter::new(params);
-    Box::into_raw(Box::new(converter))
+    into_raw(parse_params(json_params).and_then(|params: BinaryImageConverterParams| {
+        BinaryImageConverter::new(params).map_err(|err| err.to_string())
+    }))
 }
 
 #[no_mangle]
@@ -26,13 +72,10 @@ pub extern "C" fn vtracer_binary_from_bytes(
     width: usize,
     height: usize,
 ) -> *mut BinaryImageConverter {
-    let c_str = unsafe { CStr::from_ptr(json_params) };
-    let json = c_str.to_str().unwrap();
-    let params: BinaryImageConverterParams = serde_json::from_str(json).unwrap();
-
     let bytes = unsafe { slice::from_raw_parts(data, len) };
-    let converter = BinaryImageConverter::from_bytes(bytes, width, height, params);
-    Box::into_raw(Box::new(converter))
+    into_raw(parse_params(json_params).and_then(|params: BinaryImageConverterParams| {
+        BinaryImageConverter::from_bytes(bytes, width, height, params).map_err(|err| err.to_string())
+    }))
 }
 
 #[no_mangle]
@@ -64,7 +107,7 @@ pub extern "C" fn vtracer_binary_progress(ptr: *mut BinaryImageConverter) -> c_i
 #[no_mangle]
 pub extern "C" fn vtracer_binary_free(ptr: *mut BinaryImageConverter) {
     if !ptr.is_null() {
-        unsafe { Box::from_raw(ptr); }
+        unsafe { drop(Box::from_raw(ptr)); }
     }
 }
 
@@ -74,19 +117,23 @@ pub extern "C" fn vtracer_binary_free(ptr: *mut BinaryImageConverter) {
 
 #[no_mangle]
 pub extern "C" fn vtracer_color_init(json_params: *const c_char) -> *mut ColorImageConverter {
-    let c_str = unsafe { CStr::from_ptr(json_params) };
-    let json = c_str.to_str().unwrap();
-    let params: ColorImageConverterParams = serde_json::from_str(json).unwrap();
-    let converter = ColorImageConverter::new(params);
-    Box::into_raw(Box::new(converter))
+    into_raw(parse_params(json_params).and_then(|params: ColorImageConverterParams| {
+        ColorImageConverter::new(params).map_err(|err| err.to_string())
+    }))
 }
 
+/// Returns false when the image could not be keyed, in which case it must not be ticked.
+/// The reason is in `vtracer_last_error`.
 #[no_mangle]
-pub extern "C" fn vtracer_color_init_state(ptr: *mut ColorImageConverter) {
-    if !ptr.is_null() {
-        let converter = unsafe { &mut *ptr };
-        converter.init();
+pub extern "C" fn vtracer_color_init_state(ptr: *mut ColorImageConverter) -> bool {
+    if ptr.is_null() {
+        return false;
     }
+    let converter = unsafe { &mut *ptr };
+    let result = converter.init_state();
+    let ok = result.is_ok();
+    set_last_error(result.err().map(|err| err.to_string()));
+    ok
 }
 
 #[no_mangle]
@@ -97,13 +144,10 @@ pub extern "C" fn vtracer_color_from_bytes(
     width: usize,
     height: usize,
 ) -> *mut ColorImageConverter {
-    let c_str = unsafe { CStr::from_ptr(json_params) };
-    let json = c_str.to_str().unwrap();
-    let params: ColorImageConverterParams = serde_json::from_str(json).unwrap();
-
     let bytes = unsafe { slice::from_raw_parts(data, len) };
-    let converter = ColorImageConverter::from_bytes(bytes, width, height, params);
-    Box::into_raw(Box::new(converter))
+    into_raw(parse_params(json_params).and_then(|params: ColorImageConverterParams| {
+        ColorImageConverter::from_bytes(bytes, width, height, params).map_err(|err| err.to_string())
+    }))
 }
 
 #[no_mangle]
@@ -127,6 +171,6 @@ pub extern "C" fn vtracer_color_progress(ptr: *mut ColorImageConverter) -> c_int
 #[no_mangle]
 pub extern "C" fn vtracer_color_free(ptr: *mut ColorImageConverter) {
     if !ptr.is_null() {
-        unsafe { Box::from_raw(ptr); }
+        unsafe { drop(Box::from_raw(ptr)); }
     }
 }